@@ -0,0 +1,168 @@
+// the binary encoding behind the saved file format, see `format` for the
+// framing around it.
+
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::mem::size_of;
+
+use crate::format::{self, DecodeError, DecodeReason};
+use crate::TodoItem;
+
+/// Saving to and loading from a byte stream.
+///
+/// `arbed` writes the value out, `llwytho` reads one back. Problems with the
+/// data come back as `InvalidData` errors carrying a [`DecodeError`].
+pub trait Arbed where Self: Sized {
+    fn arbed<W: Write>(&self, allbwn: &mut W) -> Result<()>;
+    fn llwytho<R: Read>(mewnbwn: &mut R) -> Result<Self>;
+}
+impl Arbed for u8 {
+    fn arbed<W: Write>(&self, allbwn: &mut W) -> Result<()> {
+        allbwn.write_all(&self.to_le_bytes())
+    }
+    fn llwytho< R: Read>(mewnbwn: &mut R) -> Result<Self> {
+        let mut buff: [u8; size_of::<Self>()] = [0; size_of::<Self>()];
+        mewnbwn.read_exact(&mut buff)?;
+        Ok(Self::from_le_bytes(buff))
+    }
+}
+impl Arbed for u16 {
+    fn arbed<W: Write>(&self, allbwn: &mut W) -> Result<()> {
+        allbwn.write_all(&self.to_le_bytes())
+    }
+    fn llwytho< R: Read>(mewnbwn: &mut R) -> Result<Self> {
+        let mut buff: [u8; size_of::<Self>()] = [0; size_of::<Self>()];
+        mewnbwn.read_exact(&mut buff)?;
+        Ok(Self::from_le_bytes(buff))
+    }
+}
+impl Arbed for bool {
+    fn arbed<W: Write>(&self, allbwn:&mut W) -> Result<()> {
+        let buff = (if *self {1} else {0} as u8).to_le_bytes();
+        allbwn.write_all(&buff)?;
+        Ok(())
+    }
+    fn llwytho<R: Read>(mewnbwn: &mut R) -> Result<Self> {
+        let mut buff: [u8; 1] = [ 0 ];
+        mewnbwn.read_exact(&mut buff)?;
+        Ok(u8::from_le_bytes(buff) != 0)
+    }
+}
+/// A length, stored as an unsigned LEB128 varint: 7 bits a byte, low bits
+/// first, with the top bit set on every byte but the last.
+pub struct Hyd(pub usize);
+impl Arbed for Hyd {
+    fn arbed<W: Write>(&self, allbwn: &mut W) -> Result<()> {
+        let Ok(mut val) = u64::try_from(self.0) else {
+            return Err(Error::new(ErrorKind::InvalidInput, "length too large to store"));
+        };
+        loop {
+            let byte = (val & 0x7f) as u8;
+            val >>= 7;
+            if val == 0 {
+                return byte.arbed(allbwn);
+            }
+            (byte | 0x80).arbed(allbwn)?;
+        }
+    }
+    fn llwytho<R: Read>(mewnbwn: &mut R) -> Result<Self> {
+        let mut val = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = u8::llwytho(mewnbwn)?;
+            let bits = (byte & 0x7f) as u64;
+            if bits << shift >> shift != bits {
+                break;
+            }
+            val |= bits << shift;
+            if byte & 0x80 == 0 {
+                return match usize::try_from(val) {
+                    Ok(len) => Ok(Hyd(len)),
+                    Err(_) => Err(DecodeError::new(DecodeReason::BadLength).into()),
+                };
+            }
+        }
+        Err(DecodeError::new(DecodeReason::BadLength).into())
+    }
+}
+
+impl<T: Arbed> Arbed for Vec<T> {
+    fn arbed<W: Write>(&self, allbwn: &mut W) -> Result<()> {
+        Hyd(self.len()).arbed(allbwn)?;
+        for i in self { i.arbed(allbwn)?; }
+        Ok(())
+    }
+    fn llwytho<R: Read>(mewnbwn: &mut R) -> Result<Self> {
+        let len = Hyd::llwytho(mewnbwn)?.0;
+        let mut temp = Vec::with_capacity(format::capacity(len));
+        for _ in 0 .. len {
+            temp.push(T::llwytho(mewnbwn)?)
+        }
+        Ok(temp)
+    } 
+}
+
+impl Arbed for String {
+    fn arbed<W: Write>(&self, allbwn: &mut W) -> Result<()> {
+        Hyd(self.len()).arbed(allbwn)?;
+        allbwn.write_all(self.as_bytes())?;
+        Ok(())
+    }
+    fn llwytho<R: Read>(mewnbwn: &mut R) -> Result<Self> {
+        let len = Hyd::llwytho(mewnbwn)?.0;
+        format::llwytho_utf8(mewnbwn, len)
+    }
+}
+
+impl Arbed for TodoItem {
+    fn arbed<W: Write>(&self, allbwn: &mut W) -> Result<()> {
+        arbed_eitem(self, 0, allbwn)
+    }
+    fn llwytho<R: Read>(mewnbwn: &mut R) -> Result<Self> {
+        llwytho_eitem(mewnbwn, 0)
+    }
+}
+
+// `depth` is how many groups the item is inside, for both of these
+fn arbed_eitem<W: Write>(item: &TodoItem, depth: usize, allbwn: &mut W) -> Result<()> {
+    match item {
+        TodoItem::Task(c, msg) => {
+            u8::arbed(&0, allbwn)?;
+            c.arbed(allbwn)?;
+            msg.arbed(allbwn)?;
+        }
+        // folded groups get a tag of their own
+        TodoItem::Group(msg, xs, folded) => {
+            if depth == format::MAX_DEPTH {
+                return Err(format::too_deep());
+            }
+            u8::arbed(if *folded { &2 } else { &1 }, allbwn)?;
+            msg.arbed(allbwn)?;
+            Hyd(xs.len()).arbed(allbwn)?;
+            for x in xs {
+                arbed_eitem(x, depth + 1, allbwn)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn llwytho_eitem<R: Read>(mewnbwn: &mut R, depth: usize) -> Result<TodoItem> {
+    match u8::llwytho(mewnbwn)? {
+        0 => {
+            Ok(TodoItem::Task(bool::llwytho(mewnbwn)?, String::llwytho(mewnbwn)?))
+        }
+        tag @ (1 | 2) => {
+            if depth == format::MAX_DEPTH {
+                return Err(DecodeError::new(DecodeReason::TooDeep).back(1).into());
+            }
+            let folded = tag == 2;
+            let msg = String::llwytho(mewnbwn)?;
+            let len = Hyd::llwytho(mewnbwn)?.0;
+            let mut xs = Vec::with_capacity(format::capacity(len));
+            for i in 0 .. len {
+                xs.push(llwytho_eitem(mewnbwn, depth + 1).map_err(|e| DecodeError::in_child(e, i))?);
+            }
+            Ok(TodoItem::Group(msg, xs, folded))
+        }
+        x => Err(DecodeError::new(DecodeReason::UnknownTag(x)).back(1).into()),
+    }
+}
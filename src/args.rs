@@ -0,0 +1,126 @@
+// command line handling. Done by hand, the crate has no dependencies and
+// there's not much to parse.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use nikgwner::Toggle;
+
+pub const USAGE: &str = "\
+usage: nikgwner [OPTIONS] [FILE]
+       nikgwner export FILE [OUT]
+       nikgwner import IN FILE
+
+Opens FILE for editing, creating an empty list if it doesn't exist yet.
+`export` writes FILE out as text, to stdout if OUT isn't given, and
+`import` reads it back in as FILE.
+
+Files ending in .json are JSON and files ending in .md are Markdown task
+lists. Anything else is nikgwner's own format, or Markdown for `export`
+and `import`.
+
+options:
+    -r, --read-only           don't allow changes to the list, or saving it
+    -a, --autosave            save to FILE after every change
+        --autosave-every SECS save to FILE every SECS seconds if it has changed
+        --backups N           keep the last N saved versions as FILE.<n>.bak (default 3)
+        --undo-depth N        how many changes can be undone (default 100)
+    -b, --bars                show a progress bar beside each group
+        --partial-toggle WHAT what space does to a part done group, `complete`
+                              everything in it (the default) or `clear` it
+    -h, --help                show this message and exit
+    -V, --version             show the version and exit
+";
+
+pub struct Options {
+    pub path: Option<PathBuf>,
+    pub read_only: bool,
+    pub autosave: Autosave,
+    pub backups: usize,
+    pub undo_depth: usize,
+    pub bars: bool,
+    pub partial_toggle: Toggle,
+}
+
+pub enum Autosave {
+    Off,
+    OnChange,
+    Every(Duration),
+}
+
+pub enum Command {
+    Run(Options),
+    Export(PathBuf, Option<PathBuf>),
+    Import(PathBuf, PathBuf),
+    Help,
+    Version,
+}
+
+pub fn parse<A: Iterator<Item = String>>(mut args: A) -> Result<Command, String> {
+    let mut opts = Options {
+        path: None,
+        read_only: false,
+        autosave: Autosave::Off,
+        backups: nikgwner::storage::DEFAULT_BACKUPS,
+        undo_depth: nikgwner::history::DEFAULT_DEPTH,
+        bars: false,
+        partial_toggle: Toggle::Complete,
+    };
+    let mut flags = true;
+    let mut rest = vec![];
+    while let Some(arg) = args.next() {
+        if flags && arg.starts_with('-') && arg != "-" {
+            match arg.as_str() {
+                "--" => flags = false,
+                "-h" | "--help" => return Ok(Command::Help),
+                "-V" | "--version" => return Ok(Command::Version),
+                "-r" | "--read-only" => opts.read_only = true,
+                "-a" | "--autosave" => opts.autosave = Autosave::OnChange,
+                "-b" | "--bars" => opts.bars = true,
+                "--autosave-every" => {
+                    let secs = args.next().ok_or("`--autosave-every` needs a number of seconds")?;
+                    match secs.parse::<u64>() {
+                        Ok(secs) if secs > 0 => opts.autosave = Autosave::Every(Duration::from_secs(secs)),
+                        _ => return Err(format!("`{secs}` isn't a number of seconds")),
+                    }
+                }
+                "--backups" => {
+                    let n = args.next().ok_or("`--backups` needs a number")?;
+                    opts.backups = n.parse().map_err(|_| format!("`{n}` isn't a number"))?;
+                }
+                "--partial-toggle" => {
+                    let what = args.next().ok_or("`--partial-toggle` needs `complete` or `clear`")?;
+                    opts.partial_toggle = match what.as_str() {
+                        "complete" => Toggle::Complete,
+                        "clear" => Toggle::Clear,
+                        _ => return Err(format!("`{what}` isn't `complete` or `clear`")),
+                    };
+                }
+                "--undo-depth" => {
+                    let n = args.next().ok_or("`--undo-depth` needs a number")?;
+                    opts.undo_depth = n.parse().map_err(|_| format!("`{n}` isn't a number"))?;
+                }
+                _ => return Err(format!("unknown option `{arg}`")),
+            }
+            continue;
+        }
+        rest.push(arg);
+    }
+    let mut rest = rest.into_iter();
+    match (rest.next(), rest.next(), rest.next(), rest.next()) {
+        (Some(cmd), Some(from), to, None) if cmd == "export" => {
+            Ok(Command::Export(PathBuf::from(from), to.map(PathBuf::from)))
+        }
+        (Some(cmd), Some(from), Some(to), None) if cmd == "import" => {
+            Ok(Command::Import(PathBuf::from(from), PathBuf::from(to)))
+        }
+        (Some(cmd), _, _, _) if cmd == "export" || cmd == "import" => {
+            Err(format!("wrong number of arguments to `{cmd}`"))
+        }
+        (path, None, _, _) => {
+            opts.path = path.map(PathBuf::from);
+            Ok(Command::Run(opts))
+        }
+        (_, Some(arg), _, _) => Err(format!("unexpected argument `{arg}`")),
+    }
+}
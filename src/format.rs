@@ -0,0 +1,432 @@
+//! The on-disk framing around an `arbed` list.
+//!
+//! ```text
+//! magic   4 bytes, "NIKG"
+//! version 1 byte, `VERSION`
+//! crc     4 bytes, little endian CRC-32 of the body
+//! body    the root `TodoItem`, `arbed`
+//! ```
+//!
+//! Files from before the header existed are just the body, they're read as
+//! version 0 and get the header the next time they're saved.
+//!
+//! Versions 0 and 1 stored lengths as a `u8` child count and a `u16` title
+//! length, version 2 switched both to `Hyd` varints. Version 3 added the
+//! folded group tag.
+
+use std::fmt;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+use crate::{Arbed, TodoItem};
+
+/// The first four bytes of every saved list.
+pub const MAGIC: [u8; 4] = *b"NIKG";
+/// The format version `arbed_ffeil` writes.
+pub const VERSION: u8 = 3;
+/// How many groups deep a list can go before loading it gives up with
+/// `TooDeep`, rather than running out of stack on a hostile file. Saving
+/// refuses anything deeper too, so what's saved can always be loaded.
+pub const MAX_DEPTH: usize = 1000;
+
+/// Writes `list` with the file header in front. Lists deeper than
+/// `MAX_DEPTH` are an `InvalidInput` error.
+pub fn arbed_ffeil<W: Write>(list: &TodoItem, allbwn: &mut W) -> Result<()> {
+    let mut body = Vec::with_capacity(64);
+    list.arbed(&mut body)?;
+    allbwn.write_all(&MAGIC)?;
+    VERSION.arbed(allbwn)?;
+    allbwn.write_all(&crc32(&body).to_le_bytes())?;
+    allbwn.write_all(&body)
+}
+
+/// Reads a saved list, returning it and the version it was stored as.
+/// Anything wrong with the contents comes back as an `InvalidData` error
+/// carrying a [`DecodeError`].
+pub fn llwytho_ffeil<R: Read>(mewnbwn: &mut R) -> Result<(TodoItem, u8)> {
+    let mut buff = Vec::with_capacity(64);
+    mewnbwn.read_to_end(&mut buff)?;
+    if !buff.starts_with(&MAGIC) {
+        // headerless lists always start with a `TodoItem` tag
+        if !matches!(buff.first(), Some(0) | Some(1)) {
+            return Err(DecodeError::new(DecodeReason::NotAList).into());
+        }
+        return Ok((llwytho_corff(&buff, 0, 0)?, 0));
+    }
+    let start = MAGIC.len() + 5;
+    let Some(header) = buff.get(MAGIC.len()..start) else {
+        return Err(DecodeError::at(buff.len() as u64, DecodeReason::Truncated).into());
+    };
+    let version = header[0];
+    if version > VERSION {
+        return Err(DecodeError::at(MAGIC.len() as u64, DecodeReason::Version(version)).into());
+    }
+    let crc = u32::from_le_bytes([header[1], header[2], header[3], header[4]]);
+    let body = &buff[start..];
+    if crc32(body) != crc {
+        return Err(DecodeError::at(start as u64, DecodeReason::Checksum).into());
+    }
+    Ok((llwytho_corff(body, version, start as u64)?, version))
+}
+
+// `start` is where `body` sits in the file, for the error offsets
+fn llwytho_corff(mut body: &[u8], version: u8, start: u64) -> Result<TodoItem> {
+    let len = body.len() as u64;
+    let list = match version {
+        0 | 1 => llwytho_hen(&mut body, 0),
+        _ => TodoItem::llwytho(&mut body),
+    };
+    let pos = start + len - body.len() as u64;
+    let list = match list {
+        Ok(list) => list,
+        Err(e) => {
+            return Err(match DecodeError::from_io(e) {
+                Ok(mut e) => {
+                    e.offset = pos - e.back;
+                    e.into()
+                }
+                Err(e) => e,
+            })
+        }
+    };
+    if !body.is_empty() {
+        return Err(DecodeError::at(pos, DecodeReason::TrailingBytes).into());
+    }
+    Ok(list)
+}
+
+// the fixed width lengths of versions 0 and 1
+fn llwytho_hen<R: Read>(mewnbwn: &mut R, depth: usize) -> Result<TodoItem> {
+    let tag = u8::llwytho(mewnbwn)?;
+    if tag > 1 {
+        return Err(DecodeError::new(DecodeReason::UnknownTag(tag)).back(1).into());
+    }
+    let done = if tag == 0 { bool::llwytho(mewnbwn)? } else { false };
+    let len = u16::llwytho(mewnbwn)? as usize;
+    let msg = llwytho_utf8(mewnbwn, len)?;
+    if tag == 0 {
+        return Ok(TodoItem::Task(done, msg));
+    }
+    if depth == MAX_DEPTH {
+        return Err(DecodeError::new(DecodeReason::TooDeep).into());
+    }
+    let n = u8::llwytho(mewnbwn)? as usize;
+    let mut xs = Vec::with_capacity(n);
+    for i in 0..n {
+        xs.push(llwytho_hen(mewnbwn, depth + 1).map_err(|e| DecodeError::in_child(e, i))?);
+    }
+    Ok(TodoItem::Group(msg, xs, false))
+}
+
+/// Reads `len` bytes of UTF-8 text.
+pub fn llwytho_utf8<R: Read>(mewnbwn: &mut R, len: usize) -> Result<String> {
+    let mut buff = Vec::with_capacity(capacity(len));
+    mewnbwn.take(len as u64).read_to_end(&mut buff)?;
+    if buff.len() != len {
+        return Err(DecodeError::new(DecodeReason::Truncated).into());
+    }
+    String::from_utf8(buff).map_err(|e| {
+        let back = len - e.utf8_error().valid_up_to();
+        DecodeError::new(DecodeReason::InvalidUtf8).back(back as u64).into()
+    })
+}
+
+// the error for saving a list deeper than `MAX_DEPTH`
+pub(crate) fn too_deep() -> Error {
+    Error::new(ErrorKind::InvalidInput, format!("groups nested more than {MAX_DEPTH} deep"))
+}
+
+// What to reserve for `len` things read from a file. A corrupt or hostile
+// length could ask for far more than the file holds, so past a point the
+// buffer grows as things actually arrive instead
+pub(crate) fn capacity(len: usize) -> usize {
+    len.min(1024)
+}
+
+/// Why a list couldn't be loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeReason {
+    NotAList,
+    Version(u8),
+    Checksum,
+    UnknownTag(u8),
+    Truncated,
+    InvalidUtf8,
+    BadLength,
+    TrailingBytes,
+    TooDeep,
+}
+
+/// What went wrong loading a list, and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+    /// Bytes from the start of the file.
+    pub offset: u64,
+    /// Child indices from the root down to the item being decoded.
+    pub path: Vec<usize>,
+    pub reason: DecodeReason,
+    // while decoding, how far before the reader's position the problem is.
+    // `llwytho_corff` turns it into `offset` once it knows the position.
+    back: u64,
+}
+
+impl DecodeError {
+    /// An error at the start of the current item, with no path yet.
+    pub fn new(reason: DecodeReason) -> Self {
+        DecodeError { offset: 0, path: vec![], reason, back: 0 }
+    }
+    fn at(offset: u64, reason: DecodeReason) -> Self {
+        DecodeError { offset, ..DecodeError::new(reason) }
+    }
+    /// Marks the problem as `back` bytes before where the reader has got to.
+    pub fn back(mut self, back: u64) -> Self {
+        self.back = back;
+        self
+    }
+    /// Pulls the `DecodeError` back out of an `io::Error`. Running out of
+    /// input is `Truncated`, any other plain io error is handed back as is.
+    pub fn from_io(e: Error) -> std::result::Result<DecodeError, Error> {
+        if e.get_ref().is_some_and(|x| x.is::<DecodeError>()) {
+            return Ok(*e.into_inner().unwrap().downcast::<DecodeError>().unwrap());
+        }
+        match e.kind() {
+            ErrorKind::UnexpectedEof => Ok(DecodeError::new(DecodeReason::Truncated)),
+            _ => Err(e),
+        }
+    }
+    /// Adds child `i` to the front of the path of an error from decoding it.
+    pub fn in_child(e: Error, i: usize) -> Error {
+        match DecodeError::from_io(e) {
+            Ok(mut e) => {
+                e.path.insert(0, i);
+                e.into()
+            }
+            Err(e) => e,
+        }
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(e: DecodeError) -> Error {
+        Error::new(ErrorKind::InvalidData, e)
+    }
+}
+
+impl fmt::Display for DecodeReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeReason::NotAList => write!(f, "not a nikgwner list"),
+            DecodeReason::Version(v) => write!(f, "format version {v} is newer than this nikgwner reads ({VERSION})"),
+            DecodeReason::Checksum => write!(f, "checksum mismatch, the list is corrupted"),
+            DecodeReason::UnknownTag(t) => write!(f, "unknown item tag {t}"),
+            DecodeReason::Truncated => write!(f, "the list is cut short"),
+            DecodeReason::InvalidUtf8 => write!(f, "text isn't valid UTF-8"),
+            DecodeReason::BadLength => write!(f, "bad length"),
+            DecodeReason::TrailingBytes => write!(f, "trailing bytes after the list"),
+            DecodeReason::TooDeep => write!(f, "groups nested more than {MAX_DEPTH} deep"),
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at byte {}", self.reason, self.offset)?;
+        if let Some((first, rest)) = self.path.split_first() {
+            write!(f, ", in item {first}")?;
+            for i in rest {
+                write!(f, ".{i}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// CRC-32 (IEEE 802.3), the same one zip and png use.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for b in data {
+        crc = CRC_TABLE[((crc ^ *b as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hyd;
+
+    fn round_trip(list: &TodoItem) -> TodoItem {
+        let mut buff = Vec::new();
+        arbed_ffeil(list, &mut buff).unwrap();
+        let (out, version) = llwytho_ffeil(&mut buff.as_slice()).unwrap();
+        assert_eq!(version, VERSION);
+        out
+    }
+
+    #[test]
+    fn many_children() {
+        let xs = (0..70_000).map(|i| TodoItem::Task(i % 3 == 0, format!("task {i}"))).collect();
+        let list = TodoItem::Group(String::from("big"), xs, false);
+        assert_eq!(round_trip(&list), list);
+    }
+
+    #[test]
+    fn long_title() {
+        let list = TodoItem::Group(
+            String::from("root"),
+            vec![
+                TodoItem::Task(false, "ŵ".repeat(70_000)),
+                TodoItem::Task(true, String::from("after")),
+            ],
+            false,
+        );
+        assert_eq!(round_trip(&list), list);
+    }
+
+    #[test]
+    fn deep_tree() {
+        let mut list = TodoItem::Task(true, String::from("leaf"));
+        for i in 0..300 {
+            list = TodoItem::Group(format!("level {i}"), vec![list, TodoItem::Task(false, String::new())], false);
+        }
+        assert_eq!(round_trip(&list), list);
+    }
+
+    // `groups` untitled groups one inside the next, around a task
+    fn nested(groups: usize) -> Vec<u8> {
+        let mut body = [1, 0, 1].repeat(groups);
+        body.extend([0, 1, 0]);
+        body
+    }
+
+    #[test]
+    fn too_deep() {
+        let (list, _) = llwytho_ffeil(&mut framed(&nested(MAX_DEPTH)).as_slice()).unwrap();
+        assert_eq!(list.progress().done, 1);
+        let e = decode_error(&framed(&nested(200_000)));
+        assert_eq!(e.reason, DecodeReason::TooDeep);
+        assert_eq!(e.path.len(), MAX_DEPTH);
+        assert_eq!(e.offset, 9 + 3 * MAX_DEPTH as u64);
+        // the old format too, Group "" [...] being 1, 0, 0, 1
+        let mut old = [1, 0, 0, 1].repeat(MAX_DEPTH + 1);
+        old.extend([0, 1, 0, 0]);
+        assert_eq!(decode_error(&old).reason, DecodeReason::TooDeep);
+    }
+
+    #[test]
+    fn too_deep_to_save() {
+        let mut list = TodoItem::Task(false, String::new());
+        for _ in 0..MAX_DEPTH {
+            list = TodoItem::Group(String::new(), vec![list], false);
+        }
+        assert_eq!(round_trip(&list), list);
+        let list = TodoItem::Group(String::new(), vec![list], false);
+        let e = arbed_ffeil(&list, &mut Vec::new()).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn hyd_widths() {
+        for (len, width) in [(0, 1), (127, 1), (128, 2), (16_383, 2), (16_384, 3), (usize::MAX, 10)] {
+            let mut buff = Vec::new();
+            Hyd(len).arbed(&mut buff).unwrap();
+            assert_eq!(buff.len(), width, "{len}");
+            assert_eq!(Hyd::llwytho(&mut buff.as_slice()).unwrap().0, len);
+        }
+    }
+
+    #[test]
+    fn overlong_hyd() {
+        let buff = [0xffu8; 11];
+        assert!(Hyd::llwytho(&mut buff.as_slice()).is_err());
+    }
+
+    #[test]
+    fn headerless_v0() {
+        // Group "r" [Task(true, "a")], with the old u16/u8 lengths
+        let buff = [1u8, 1, 0, b'r', 1, 0, 1, 1, 0, b'a'];
+        let (list, version) = llwytho_ffeil(&mut buff.as_slice()).unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(
+            list,
+            TodoItem::Group(String::from("r"), vec![TodoItem::Task(true, String::from("a"))], false),
+        );
+    }
+
+    fn decode_error(buff: &[u8]) -> DecodeError {
+        let e = llwytho_ffeil(&mut &buff[..]).unwrap_err();
+        DecodeError::from_io(e).unwrap()
+    }
+
+    fn framed(body: &[u8]) -> Vec<u8> {
+        let mut buff = MAGIC.to_vec();
+        buff.push(VERSION);
+        buff.extend(crc32(body).to_le_bytes());
+        buff.extend(body);
+        buff
+    }
+
+    #[test]
+    fn unknown_tag() {
+        // Group "r" [Task(false, "a"), <tag 7>]
+        let e = decode_error(&framed(&[1, 1, b'r', 2, 0, 0, 1, b'a', 7]));
+        assert_eq!(e.reason, DecodeReason::UnknownTag(7));
+        assert_eq!(e.path, vec![1]);
+        assert_eq!(e.offset, 9 + 8);
+    }
+
+    #[test]
+    fn truncated() {
+        // Group "r" [Group "" [Task(false, 5 bytes of "a")]]
+        let e = decode_error(&framed(&[1, 1, b'r', 1, 1, 0, 1, 0, 0, 5, b'a']));
+        assert_eq!(e.reason, DecodeReason::Truncated);
+        assert_eq!(e.path, vec![0, 0]);
+        assert_eq!(e.offset, 9 + 11);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let e = decode_error(&framed(&[0, 0, 3, b'a', 0xff, b'b']));
+        assert_eq!(e.reason, DecodeReason::InvalidUtf8);
+        assert_eq!(e.path, vec![]);
+        assert_eq!(e.offset, 9 + 4);
+    }
+
+    #[test]
+    fn bad_length() {
+        let mut body = vec![1, 0];
+        body.extend([0xff; 11]);
+        assert_eq!(decode_error(&framed(&body)).reason, DecodeReason::BadLength);
+    }
+
+    #[test]
+    fn not_a_list() {
+        assert_eq!(decode_error(b"hello").reason, DecodeReason::NotAList);
+    }
+
+    #[test]
+    fn corrupt_body() {
+        let list = TodoItem::Group(String::from("r"), vec![TodoItem::Task(false, String::from("a"))], false);
+        let mut buff = Vec::new();
+        arbed_ffeil(&list, &mut buff).unwrap();
+        *buff.last_mut().unwrap() ^= 1;
+        assert_eq!(decode_error(&buff).reason, DecodeReason::Checksum);
+    }
+}
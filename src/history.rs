@@ -0,0 +1,103 @@
+//! Undo and redo.
+//!
+//! Every entry is a whole copy of the tree and the selection from just
+//! before a change. Lists are small enough that this is simpler than
+//! recording what each change did, and it can't get out of step with them.
+
+use std::collections::VecDeque;
+
+use crate::{Selection, TodoItem};
+
+/// How many changes can be undone unless told otherwise.
+pub const DEFAULT_DEPTH: usize = 100;
+
+/// The undo and redo stacks for one list.
+pub struct History {
+    undo: VecDeque<(TodoItem, Selection)>,
+    redo: Vec<(TodoItem, Selection)>,
+    depth: usize,
+}
+
+impl History {
+    /// Remembers at most `depth` changes, 0 turns undo off.
+    pub fn new(depth: usize) -> Self {
+        History { undo: VecDeque::new(), redo: vec![], depth }
+    }
+    /// Call just before changing `list`. Anything that was undone can't be
+    /// redone after this.
+    pub fn record(&mut self, list: &TodoItem, sel: &Selection) {
+        self.redo.clear();
+        if self.depth == 0 {
+            return;
+        }
+        if self.undo.len() == self.depth {
+            self.undo.pop_front();
+        }
+        self.undo.push_back((list.clone(), sel.clone()));
+    }
+    /// Puts back the state from before the last change. Returns `false` if
+    /// there was nothing to undo.
+    pub fn undo(&mut self, list: &mut TodoItem, sel: &mut Selection) -> bool {
+        let Some((l, s)) = self.undo.pop_back() else {
+            return false;
+        };
+        self.redo.push((std::mem::replace(list, l), std::mem::replace(sel, s)));
+        true
+    }
+    /// Reapplies the last undone change. Returns `false` if there was
+    /// nothing to redo.
+    pub fn redo(&mut self, list: &mut TodoItem, sel: &mut Selection) -> bool {
+        let Some((l, s)) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push_back((std::mem::replace(list, l), std::mem::replace(sel, s)));
+        true
+    }
+    /// Forgets everything, for when a different list is loaded.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(n: usize) -> TodoItem {
+        TodoItem::Group(String::from("root"), (0..n).map(|i| TodoItem::Task(false, i.to_string())).collect(), false)
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut history = History::new(10);
+        let mut l = list(1);
+        let mut sel = Selection(vec![0]);
+        history.record(&l, &sel);
+        l = list(2);
+        sel = Selection(vec![1]);
+        assert!(history.undo(&mut l, &mut sel));
+        assert_eq!((&l, &sel), (&list(1), &Selection(vec![0])));
+        assert!(!history.undo(&mut l, &mut sel));
+        assert!(history.redo(&mut l, &mut sel));
+        assert_eq!((&l, &sel), (&list(2), &Selection(vec![1])));
+        assert!(!history.redo(&mut l, &mut sel));
+    }
+
+    #[test]
+    fn depth_and_new_changes() {
+        let mut history = History::new(2);
+        let mut l = list(0);
+        let mut sel = Selection::default();
+        for n in 1..=3 {
+            history.record(&l, &sel);
+            l = list(n);
+        }
+        assert!(history.undo(&mut l, &mut sel));
+        assert!(history.undo(&mut l, &mut sel));
+        assert_eq!(l, list(1));
+        assert!(!history.undo(&mut l, &mut sel));
+        history.record(&l, &sel);
+        assert!(!history.redo(&mut l, &mut sel));
+    }
+}
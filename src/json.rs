@@ -0,0 +1,421 @@
+//! Lists as JSON, for other tools to read and write.
+//!
+//! ```text
+//! {"title": "root", "done": false, "children": [
+//!   {"title": "a task", "done": true}
+//! ]}
+//! ```
+//!
+//! An object with `children` is a group, even if the array is empty, and
+//! anything else is a task. A group's `done` is written from `completed` and
+//! ignored on the way back in, like in the Markdown format. Folded groups
+//! have `"folded": true`, it's left out otherwise.
+//!
+//! There's no serde to lean on, so this is a small parser of its own. Syntax
+//! errors are reported by line and column, and a document that parses but
+//! isn't a list by the JSON path of the bad value, like `$.children[2].title`.
+
+use std::io::{Error, ErrorKind, Result, Write};
+
+use crate::format;
+use crate::TodoItem;
+
+/// Writes `list` as indented JSON. Lists deeper than `format::MAX_DEPTH`
+/// are an `InvalidInput` error, the same as for the binary format.
+pub fn arbed_json<W: Write>(list: &TodoItem, allbwn: &mut W) -> Result<()> {
+    arbed_eitem(list, 0, allbwn)?;
+    writeln!(allbwn)
+}
+
+fn arbed_eitem<W: Write>(item: &TodoItem, depth: usize, allbwn: &mut W) -> Result<()> {
+    let indent = "  ".repeat(depth + 1);
+    write!(allbwn, "{{\n{indent}\"title\": ")?;
+    arbed_string(item.message(), allbwn)?;
+    write!(allbwn, ",\n{indent}\"done\": {}", item.completed())?;
+    if let TodoItem::Group(_, xs, folded) = item {
+        // each group indents its children by two levels
+        if depth / 2 == format::MAX_DEPTH {
+            return Err(format::too_deep());
+        }
+        if *folded {
+            write!(allbwn, ",\n{indent}\"folded\": true")?;
+        }
+        write!(allbwn, ",\n{indent}\"children\": [")?;
+        for (i, x) in xs.iter().enumerate() {
+            write!(allbwn, "{}\n{indent}  ", if i == 0 { "" } else { "," })?;
+            arbed_eitem(x, depth + 2, allbwn)?;
+        }
+        if !xs.is_empty() {
+            write!(allbwn, "\n{indent}")?;
+        }
+        write!(allbwn, "]")?;
+    }
+    write!(allbwn, "\n{}}}", "  ".repeat(depth))
+}
+
+fn arbed_string<W: Write>(text: &str, allbwn: &mut W) -> Result<()> {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    allbwn.write_all(out.as_bytes())
+}
+
+/// Parses a list from JSON text.
+pub fn llwytho_json(text: &str) -> Result<TodoItem> {
+    let mut parser = Parser { text, pos: 0, depth: 0 };
+    parser.space();
+    let value = parser.value()?;
+    parser.space();
+    if parser.pos != text.len() {
+        return Err(parser.error("trailing characters after the list"));
+    }
+    let mut path = String::from("$");
+    item(&value, &mut path)
+}
+
+fn item(value: &Value, path: &mut String) -> Result<TodoItem> {
+    let Value::Object(fields) = value else {
+        return Err(path_error(path, "expected an object"));
+    };
+    let mut title = None;
+    let mut done = false;
+    let mut folded = false;
+    let mut children = None;
+    for (key, value) in fields {
+        let len = path.len();
+        path.push('.');
+        path.push_str(key);
+        match (key.as_str(), value) {
+            ("title", Value::String(x)) => title = Some(x.clone()),
+            ("title", _) => return Err(path_error(path, "expected a string")),
+            ("done", Value::Bool(x)) => done = *x,
+            ("done", _) => return Err(path_error(path, "expected true or false")),
+            ("folded", Value::Bool(x)) => folded = *x,
+            ("folded", _) => return Err(path_error(path, "expected true or false")),
+            ("children", Value::Array(xs)) => {
+                let mut out = Vec::with_capacity(xs.len());
+                for (i, x) in xs.iter().enumerate() {
+                    let len = path.len();
+                    path.push_str(&format!("[{i}]"));
+                    out.push(item(x, path)?);
+                    path.truncate(len);
+                }
+                children = Some(out);
+            }
+            ("children", _) => return Err(path_error(path, "expected an array")),
+            _ => return Err(path_error(path, "unknown key")),
+        }
+        path.truncate(len);
+    }
+    let Some(title) = title else {
+        return Err(path_error(path, "missing `title`"));
+    };
+    Ok(match children {
+        Some(xs) => TodoItem::Group(title, xs, folded),
+        None => TodoItem::Task(done, title),
+    })
+}
+
+fn path_error(path: &str, reason: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("{path}: {reason}"))
+}
+
+enum Value {
+    Null,
+    Bool(bool),
+    Number,
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    // how many objects and arrays the parser is inside
+    depth: usize,
+}
+
+// every group is an object holding a `children` array, with the tasks in
+// the deepest ones being objects again
+const MAX_NESTING: usize = 2 * format::MAX_DEPTH + 1;
+
+impl Parser<'_> {
+    fn value(&mut self) -> Result<Value> {
+        match self.peek() {
+            Some('{' | '[') if self.depth == MAX_NESTING => Err(self.error("nested too deeply")),
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('t') => self.word("true", Value::Bool(true)),
+            Some('f') => self.word("false", Value::Bool(false)),
+            Some('n') => self.word("null", Value::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+    fn nested(&mut self, f: fn(&mut Self) -> Result<Value>) -> Result<Value> {
+        self.depth += 1;
+        let out = f(self);
+        self.depth -= 1;
+        out
+    }
+    fn object(&mut self) -> Result<Value> {
+        self.pos += 1;
+        let mut fields: Vec<(String, Value)> = vec![];
+        self.space();
+        if self.eat('}') {
+            return Ok(Value::Object(fields));
+        }
+        loop {
+            self.space();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a key"));
+            }
+            let key = self.string()?;
+            if fields.iter().any(|(k, _)| *k == key) {
+                return Err(self.error("duplicate key"));
+            }
+            self.space();
+            if !self.eat(':') {
+                return Err(self.error("expected `:`"));
+            }
+            self.space();
+            fields.push((key, self.value()?));
+            self.space();
+            if self.eat('}') {
+                return Ok(Value::Object(fields));
+            }
+            if !self.eat(',') {
+                return Err(self.error("expected `,` or `}`"));
+            }
+        }
+    }
+    fn array(&mut self) -> Result<Value> {
+        self.pos += 1;
+        let mut xs = vec![];
+        self.space();
+        if self.eat(']') {
+            return Ok(Value::Array(xs));
+        }
+        loop {
+            self.space();
+            xs.push(self.value()?);
+            self.space();
+            if self.eat(']') {
+                return Ok(Value::Array(xs));
+            }
+            if !self.eat(',') {
+                return Err(self.error("expected `,` or `]`"));
+            }
+        }
+    }
+    fn string(&mut self) -> Result<String> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(out),
+                '\\' => out.push(self.escape()?),
+                c if (c as u32) < 0x20 => {
+                    self.pos -= 1;
+                    return Err(self.error("control character in string"));
+                }
+                c => out.push(c),
+            }
+        }
+    }
+    fn escape(&mut self) -> Result<char> {
+        let Some(c) = self.peek() else {
+            return Err(self.error("unterminated string"));
+        };
+        self.pos += c.len_utf8();
+        Ok(match c {
+            '"' => '"',
+            '\\' => '\\',
+            '/' => '/',
+            'b' => '\x08',
+            'f' => '\x0c',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let high = self.hex4()?;
+                let code = if (0xd800..0xdc00).contains(&high) {
+                    if !self.text[self.pos..].starts_with("\\u") {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    self.pos += 2;
+                    let low = self.hex4()?;
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                } else {
+                    high
+                };
+                match char::from_u32(code) {
+                    Some(c) => c,
+                    None => return Err(self.error("unpaired surrogate")),
+                }
+            }
+            _ => {
+                self.pos -= c.len_utf8();
+                return Err(self.error("unknown escape"));
+            }
+        })
+    }
+    fn hex4(&mut self) -> Result<u32> {
+        match self.text.get(self.pos..self.pos + 4) {
+            Some(x) if x.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                self.pos += 4;
+                Ok(u32::from_str_radix(x, 16).unwrap())
+            }
+            _ => Err(self.error("expected four hex digits")),
+        }
+    }
+    // numbers are never part of a list, they're only checked for shape
+    fn number(&mut self) -> Result<Value> {
+        self.eat('-');
+        if !self.digits() {
+            return Err(self.error("expected a digit"));
+        }
+        if self.eat('.') && !self.digits() {
+            return Err(self.error("expected a digit"));
+        }
+        if self.eat('e') || self.eat('E') {
+            let _ = self.eat('+') || self.eat('-');
+            if !self.digits() {
+                return Err(self.error("expected a digit"));
+            }
+        }
+        Ok(Value::Number)
+    }
+    fn digits(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+    fn word(&mut self, word: &str, value: Value) -> Result<Value> {
+        if !self.text[self.pos..].starts_with(word) {
+            return Err(self.error("expected a value"));
+        }
+        self.pos += word.len();
+        Ok(value)
+    }
+    fn space(&mut self) {
+        while self.peek().is_some_and(|c| matches!(c, ' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            return true;
+        }
+        false
+    }
+    fn error(&self, reason: &str) -> Error {
+        let before = &self.text[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+        Error::new(ErrorKind::InvalidData, format!("line {line}, column {column}: {reason}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(done: bool, msg: &str) -> TodoItem {
+        TodoItem::Task(done, String::from(msg))
+    }
+
+    #[test]
+    fn round_trip() {
+        let list = TodoItem::Group(
+            String::from("root \"quoted\" \\ \n\t\u{1}"),
+            vec![
+                TodoItem::Group(String::from("a"), vec![task(true, "a.1"), task(false, "ŵ 🦀")], false),
+                TodoItem::Group(String::from("empty"), vec![], false),
+                task(true, "b"),
+            ],
+            false,
+        );
+        let mut buff = Vec::new();
+        arbed_json(&list, &mut buff).unwrap();
+        let text = String::from_utf8(buff).unwrap();
+        assert_eq!(llwytho_json(&text).unwrap(), list);
+    }
+
+    #[test]
+    fn escapes() {
+        let list = llwytho_json(r#"{"title": "é🦀\/", "extra": null}"#);
+        assert_eq!(list.unwrap_err().to_string(), "$.extra: unknown key");
+        let list = llwytho_json(r#" {"title": "é🦀\/"} "#).unwrap();
+        assert_eq!(list, task(false, "é🦀/"));
+    }
+
+    #[test]
+    fn structure_errors() {
+        let e = llwytho_json(r#"{"title": "r", "children": [{"title": "a"}, {"title": 3}]}"#);
+        assert_eq!(e.unwrap_err().to_string(), "$.children[1].title: expected a string");
+        let e = llwytho_json(r#"{"children": [{"title": "a", "done": "yes"}]}"#);
+        assert_eq!(e.unwrap_err().to_string(), "$.children[0].done: expected true or false");
+        let e = llwytho_json(r#"{"children": []}"#);
+        assert_eq!(e.unwrap_err().to_string(), "$: missing `title`");
+        let e = llwytho_json(r#"[1.5e3, -2]"#);
+        assert_eq!(e.unwrap_err().to_string(), "$: expected an object");
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let e = llwytho_json("{\n  \"title\": \"a\",\n  }");
+        assert_eq!(e.unwrap_err().to_string(), "line 3, column 3: expected a key");
+        let e = llwytho_json(r#"{"title": "a"} x"#);
+        assert_eq!(e.unwrap_err().to_string(), "line 1, column 16: trailing characters after the list");
+        let e = llwytho_json(r#"{"title": "a"#);
+        assert_eq!(e.unwrap_err().to_string(), "line 1, column 13: unterminated string");
+    }
+
+    #[test]
+    fn too_deep() {
+        let nested = |groups: usize| {
+            let mut text = r#"{"title": "", "children": ["#.repeat(groups);
+            text.push_str(r#"{"title": "a"}"#);
+            text.push_str(&"]}".repeat(groups));
+            text
+        };
+        let list = llwytho_json(&nested(format::MAX_DEPTH)).unwrap();
+        assert_eq!(list.progress().total, 1);
+        let mut buff = Vec::new();
+        arbed_json(&list, &mut buff).unwrap();
+        let list = TodoItem::Group(String::new(), vec![list], false);
+        let e = arbed_json(&list, &mut Vec::new()).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+        let e = llwytho_json(&nested(format::MAX_DEPTH + 1)).unwrap_err();
+        assert!(e.to_string().ends_with("nested too deeply"), "{e}");
+        assert!(llwytho_json(&"[".repeat(200_000)).is_err());
+    }
+}
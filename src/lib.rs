@@ -0,0 +1,158 @@
+//! The todo list model behind nikgwner, and its file formats.
+//!
+//! A list is a tree of [`TodoItem`]s, with a [`Selection`] pointing into it
+//! that the navigation methods on `TodoItem` move around. [`storage`] gets
+//! lists on and off disk in whichever [`storage::Format`] the file name asks
+//! for, with [`mod@format`], [`markdown`] and [`json`] doing the encoding.
+//! [`history`] and [`registers`] hold the editor's undo stack and clipboard.
+//!
+//! ```
+//! use nikgwner::{CursMove, Selection, TodoItem};
+//!
+//! let mut list = TodoItem::Group(String::from("chores"), vec![], false);
+//! list.insert(TodoItem::Task(false, String::from("dishes")));
+//! let mut sel = Selection::default();
+//! list.do_move(&mut sel, CursMove::In);
+//! list.get_mut(&sel).unwrap().complete(true);
+//! assert!(list.completed());
+//! ```
+
+mod arbed;
+mod todo;
+
+pub mod format;
+pub mod history;
+pub mod json;
+pub mod markdown;
+pub mod registers;
+pub mod storage;
+
+pub use arbed::{Arbed, Hyd};
+pub use todo::{Check, CursMove, Line, Progress, Selection, Toggle, TodoItem};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use format::{DecodeError, DecodeReason};
+    use std::io::Cursor;
+
+    // xorshift64*, enough randomness to shake out the encoders without
+    // pulling in a crate
+    struct Rng(u64);
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    const ALPHABET: &[&str] = &["a", "b", "z", "0", " ", "ŵ", "🦀", "\"", "\\", "[x]", "-"];
+
+    fn title(rng: &mut Rng) -> String {
+        // now and then long enough for a multi-byte `Hyd`
+        let len = if rng.below(10) == 0 { 200 } else { rng.below(12) };
+        let mut out: String = (0..len).map(|_| ALPHABET[rng.below(ALPHABET.len())]).collect();
+        // the Markdown format trims titles
+        out = out.trim().to_string();
+        if out.is_empty() {
+            out.push('t');
+        }
+        out
+    }
+
+    fn tree(rng: &mut Rng, depth: usize) -> TodoItem {
+        if depth == 0 || rng.below(3) == 0 {
+            return TodoItem::Task(rng.below(2) == 0, title(rng));
+        }
+        let xs = (0..rng.below(6)).map(|_| tree(rng, depth - 1)).collect();
+        TodoItem::Group(title(rng), xs, rng.below(4) == 0)
+    }
+
+    fn trees() -> impl Iterator<Item = TodoItem> {
+        (1..=200u64).map(|seed| {
+            let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+            TodoItem::Group(title(&mut rng), (0..rng.below(8)).map(|_| tree(&mut rng, 5)).collect(), false)
+        })
+    }
+
+    fn arbed(list: &TodoItem) -> Vec<u8> {
+        let mut buff = Vec::new();
+        list.arbed(&mut buff).unwrap();
+        buff
+    }
+
+    #[test]
+    fn arbed_round_trip() {
+        for list in trees() {
+            let buff = arbed(&list);
+            let mut cursor = Cursor::new(&buff);
+            assert_eq!(TodoItem::llwytho(&mut cursor).unwrap(), list);
+            assert_eq!(cursor.position() as usize, buff.len());
+        }
+    }
+
+    #[test]
+    fn file_round_trip() {
+        for list in trees() {
+            let mut buff = Vec::new();
+            format::arbed_ffeil(&list, &mut buff).unwrap();
+            let (out, _) = format::llwytho_ffeil(&mut Cursor::new(buff)).unwrap();
+            assert_eq!(out, list);
+        }
+    }
+
+    #[test]
+    fn truncated_never_panics() {
+        for list in trees().take(40) {
+            let buff = arbed(&list);
+            for end in (0..buff.len()).step_by(buff.len() / 64 + 1) {
+                let e = TodoItem::llwytho(&mut Cursor::new(&buff[..end])).unwrap_err();
+                assert_eq!(DecodeError::from_io(e).unwrap().reason, DecodeReason::Truncated);
+            }
+        }
+    }
+
+    #[test]
+    fn garbage_never_panics() {
+        let mut rng = Rng(7);
+        for _ in 0..2000 {
+            let buff: Vec<u8> = (0..rng.below(64)).map(|_| rng.next() as u8).collect();
+            let _ = format::llwytho_ffeil(&mut Cursor::new(buff));
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        for list in trees() {
+            let mut buff = Vec::new();
+            json::arbed_json(&list, &mut buff).unwrap();
+            assert_eq!(json::llwytho_json(&String::from_utf8(buff).unwrap()).unwrap(), list);
+        }
+    }
+
+    // Markdown can't tell an empty group from a finished task
+    fn without_empty_groups(list: &TodoItem) -> TodoItem {
+        match list {
+            TodoItem::Group(msg, xs, _) if xs.is_empty() => TodoItem::Task(true, msg.clone()),
+            TodoItem::Group(msg, xs, _) => TodoItem::Group(msg.clone(), xs.iter().map(without_empty_groups).collect(), false),
+            x => x.clone(),
+        }
+    }
+
+    #[test]
+    fn markdown_round_trip() {
+        for list in trees() {
+            let mut buff = Vec::new();
+            markdown::arbed_md(&list, &mut buff).unwrap();
+            let out = markdown::llwytho_md(&String::from_utf8(buff).unwrap(), "unused").unwrap();
+            let TodoItem::Group(msg, xs, _) = &list else { unreachable!() };
+            let expected = TodoItem::Group(msg.clone(), xs.iter().map(without_empty_groups).collect(), false);
+            assert_eq!(out, expected);
+        }
+    }
+}
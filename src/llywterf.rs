@@ -5,6 +5,7 @@
 use std::io::{self, Read, Write, BufRead};
 use std::mem::MaybeUninit;
 use std::os::fd::{AsRawFd, RawFd};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 const NCCS: usize = 32;
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -20,18 +21,91 @@ struct termios {
     c_ospeed: u32,
 }
 
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+struct winsize {
+    ws_row: u16,
+    ws_col: u16,
+    ws_xpixel: u16,
+    ws_ypixel: u16,
+}
+
 #[link(name = "c")]
 extern "C" {
     fn tcsetattr(fd: RawFd, optional_actions: i32, termios_p: *const termios) -> i32;
     fn tcgetattr(fd: RawFd, termios_p: *mut termios) -> i32;
+    fn ioctl(fd: RawFd, request: u64, ...) -> i32;
+    fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    fn siginterrupt(sig: i32, flag: i32) -> i32;
+}
+
+// set by the SIGWINCH handler, cleared by `aros` when it reports it
+static MAINT_NEWID: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn ar_newid_maint(_: i32) {
+    MAINT_NEWID.store(true, Ordering::Relaxed);
+}
+
+// what `aros` waited for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Digwyddiad {
+    // a key was pressed, with these modifiers held
+    Bysell(Key, Mods),
+    // the terminal changed size, see `maint`
+    Maint,
+    // the read timed out, only with `stopi(false)`
+    Amser,
+}
+
+// a key, once any escape sequence for it has been decoded. Control
+// characters like ctrl-r come through as `Char`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    Escape,
+    F(u8),
+}
+
+// the modifiers the terminal reported along with a key. Alt is the only one
+// that shows up with `Key::Char`, shift is already in the character
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Mods {
+    pub shift: bool,
+    pub alt: bool,
+    pub ctrl: bool,
+}
+
+impl Mods {
+    pub const NONE: Mods = Mods { shift: false, alt: false, ctrl: false };
+    const ALT: Mods = Mods { shift: false, alt: true, ctrl: false };
+    // from the second parameter of a CSI sequence, 1 plus a bit for each
+    fn o_csi(n: u8) -> Self {
+        let bits = n.saturating_sub(1);
+        Mods { shift: bits & 1 != 0, alt: bits & 2 != 0, ctrl: bits & 4 != 0 }
+    }
 }
 
+// the rest of an escape sequence comes in straight after the ESC, a pause
+// longer than this, in tenths of a second, means it was the escape key
+const ESC_AROS: u8 = 1;
+
 pub struct TerfLleol<O: Write + AsRawFd, I: Read + AsRawFd> {
     allbwn: O,
     mewnbwn: I,
     llawnsgrin: bool,
     blaen: termios,
     cyfred: termios,
+    datgodiwr: Datgodiwr,
 }
 
 impl<O: Write + AsRawFd, I: Read + AsRawFd> TerfLleol<O, I> {
@@ -46,39 +120,88 @@ impl<O: Write + AsRawFd, I: Read + AsRawFd> TerfLleol<O, I> {
                     llawnsgrin: false,
                     blaen: temp,
                     cyfred: temp,
+                    datgodiwr: Datgodiwr::default(),
                 })
             }
             Err(err) => Err(err),
         }
     }
     pub fn newid<'a>(&'a mut self) -> Newidiad<'a, O, I> {
-        let blaen = self.cyfred.clone();
+        let blaen = self.cyfred;
         Newidiad{
             targed: self,
-            blaen: blaen,
+            blaen,
             llawnsgrin: None,
         }
     }
-    pub fn ungell(&mut self) -> io::Result<Option<char>> {
-        // currently only supports up to 4 byte utf8 strings
-        let mut init_buff: [u8; 1] = [0; 1];
-        if self.mewnbwn.read(&mut init_buff)? == 0 {
-            return Ok(None);
+    // the size of the terminal as (rows, columns)
+    pub fn maint(&self) -> io::Result<(u16, u16)> {
+        let mut maint = winsize::default();
+        io_result(unsafe { ioctl(self.allbwn.as_raw_fd(), TIOCGWINSZ, &mut maint as *mut winsize) })?;
+        Ok((maint.ws_row, maint.ws_col))
+    }
+    // starts listening for the terminal changing size, which `aros` then
+    // reports. Reads are left to be interrupted by it rather than restarted,
+    // so a blocked read wakes up for it
+    pub fn gwrando_maint(&mut self) -> io::Result<()> {
+        if unsafe { signal(SIGWINCH, ar_newid_maint) } == SIG_ERR {
+            return Err(io::Error::last_os_error());
         }
-        if !utf8_start(init_buff[0]) {
-            return Ok(None);
+        io_result(unsafe { siginterrupt(SIGWINCH, 1) })
+    }
+    // waits for a key or a resize. `None` if the input is gone
+    pub fn aros(&mut self) -> io::Result<Option<Digwyddiad>> {
+        loop {
+            if MAINT_NEWID.swap(false, Ordering::Relaxed) {
+                return Ok(Some(Digwyddiad::Maint));
+            }
+            match self.ungell() {
+                Ok(Some('\x1b')) => match dilyniant(|| self.ungell_buan())? {
+                    Some((key, mods)) => return Ok(Some(Digwyddiad::Bysell(key, mods))),
+                    // a sequence we don't know, skip it
+                    None => continue,
+                },
+                Ok(Some(c)) => return Ok(Some(Digwyddiad::Bysell(Key::Char(c), Mods::NONE))),
+                Ok(None) if self.yn_stopi() => return Ok(None),
+                Ok(None) => return Ok(Some(Digwyddiad::Amser)),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
         }
-        if utf8_len(init_buff[0]) == 1 {
-            return Ok(Some(init_buff[0] as char));
+    }
+    // reads a character, giving up after `ESC_AROS`
+    fn ungell_buan(&mut self) -> io::Result<Option<char>> {
+        let blaen = self.cyfred;
+        self.newid().stopi(false).amser(ESC_AROS).atod()?;
+        let c = match self.ungell() {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => Ok(None),
+            c => c,
+        };
+        self.cyfred = blaen;
+        io_result(unsafe { tcsetattr(self.allbwn.as_raw_fd(), TCSANOW, &blaen) })?;
+        c
+    }
+    // whether reads wait for input, see `Newidiad::stopi`
+    pub fn yn_stopi(&self) -> bool {
+        self.cyfred.c_cc[VMIN] != 0
+    }
+    // reads a character. `None` if nothing came, which with `stopi(true)`
+    // means the input is gone
+    pub fn ungell(&mut self) -> io::Result<Option<char>> {
+        if let Some(c) = self.datgodiwr.nesaf.take() {
+            return Ok(Some(c));
+        }
+        let mut byte = [0; 1];
+        loop {
+            if self.mewnbwn.read(&mut byte)? == 0 {
+                // a timeout might be in the middle of a character, keep what
+                // there is of it. The end of the input won't finish it though
+                return Ok(if self.yn_stopi() { self.datgodiwr.gorffen() } else { None });
+            }
+            if let Some(c) = self.datgodiwr.bwydo(byte[0]) {
+                return Ok(Some(c));
+            }
         }
-
-        let mut rest_buff = vec![0; utf8_len(init_buff[0])];
-        let _ = self.mewnbwn.read(&mut rest_buff)?;
-        rest_buff.insert(0, init_buff[0]);
-        return Ok(String::from_utf8(rest_buff)
-            .expect("fatal error")
-            .chars()
-            .next());
     }
 }
 impl<O: Write + AsRawFd, I: Read + AsRawFd> Drop for TerfLleol<O, I> {
@@ -99,12 +222,12 @@ impl<'a, O: Write + AsRawFd, I: Read + AsRawFd> Newidiad<'a, O, I> {
     pub fn atod(&mut self) -> io::Result<()> {
         self.targed.cyfred = self.blaen;
         unsafe {
-            if self.llawnsgrin.is_some() {
-            self.targed.llawnsgrin = self.llawnsgrin.unwrap();
-            if self.llawnsgrin.unwrap() {
-                let _ = self.targed.allbwn.write(b"\x1b[1049h")?;
+            if let Some(llawnsgrin) = self.llawnsgrin {
+            self.targed.llawnsgrin = llawnsgrin;
+            if llawnsgrin {
+                self.targed.allbwn.write_all(b"\x1b[1049h")?;
             } else {
-                let _ = self.targed.allbwn.write(b"\x1b[1049l")?;
+                self.targed.allbwn.write_all(b"\x1b[1049l")?;
             }
             }
             io_result(tcsetattr(self.targed.allbwn.as_raw_fd(), TCSANOW, &self.blaen))
@@ -128,6 +251,12 @@ impl<'a, O: Write + AsRawFd, I: Read + AsRawFd> Newidiad<'a, O, I> {
         self.blaen.c_cc[VMIN] = if value { 1 } else { 0 };
         self
     }
+    // how long a read waits for input, in tenths of a second. Only matters
+    // with `stopi(false)`, where a read that times out returns nothing.
+    pub fn amser(&mut self, deg_eiliad: u8) -> &mut Self {
+        self.blaen.c_cc[VTIME] = deg_eiliad;
+        self
+    }
     pub fn llawnsgrin(&mut self, value: bool) -> &mut Self {
         self.llawnsgrin = Some(value);
         self
@@ -156,6 +285,58 @@ impl<O: Write + AsRawFd, I: BufRead + AsRawFd> BufRead for TerfLleol<O, I> {
     }
 }
 
+// works out the key from what follows an ESC, `nesaf` giving the next
+// character or `None` if there's a pause. Handles CSI (`ESC [`) and SS3
+// (`ESC O`) sequences, ESC before anything else is alt held with it.
+// `None` for a sequence that isn't a key we know
+fn dilyniant(mut nesaf: impl FnMut() -> io::Result<Option<char>>) -> io::Result<Option<(Key, Mods)>> {
+    let csi = match nesaf()? {
+        None => return Ok(Some((Key::Escape, Mods::NONE))),
+        Some('[') => true,
+        Some('O') => false,
+        Some(c) => return Ok(Some((Key::Char(c), Mods::ALT))),
+    };
+    // parameter bytes, then the final byte that says what it is
+    let mut params = String::new();
+    let last = loop {
+        match nesaf()? {
+            Some(c @ '0'..='?') if csi => params.push(c),
+            Some(c @ '@'..='~') => break c,
+            // just alt-[ or alt-O
+            None if params.is_empty() => return Ok(Some((Key::Char(if csi { '[' } else { 'O' }), Mods::ALT))),
+            _ => return Ok(None),
+        }
+    };
+    let mut params = params.split(';').map(|x| x.parse::<u8>().ok());
+    let first = params.next().flatten();
+    let mods = params.next().flatten().map_or(Mods::NONE, Mods::o_csi);
+    let key = match last {
+        'A' => Key::Up,
+        'B' => Key::Down,
+        'C' => Key::Right,
+        'D' => Key::Left,
+        'H' => Key::Home,
+        'F' => Key::End,
+        'P'..='S' => Key::F(last as u8 - b'P' + 1),
+        // shift-tab
+        'Z' => return Ok(Some((Key::Char('\t'), Mods { shift: true, ..mods }))),
+        '~' => match first {
+            Some(1 | 7) => Key::Home,
+            Some(2) => Key::Insert,
+            Some(3) => Key::Delete,
+            Some(4 | 8) => Key::End,
+            Some(5) => Key::PageUp,
+            Some(6) => Key::PageDown,
+            Some(n @ 11..=15) => Key::F(n - 10),
+            Some(n @ 17..=21) => Key::F(n - 11),
+            Some(n @ 23..=24) => Key::F(n - 12),
+            _ => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some((key, mods)))
+}
+
 #[inline(always)]
 fn io_result(result: i32) -> io::Result<()> {
     match result {
@@ -163,32 +344,133 @@ fn io_result(result: i32) -> io::Result<()> {
         _ => Err(io::Error::last_os_error()),
     }
 }
-#[inline(always)]
-pub fn utf8_start(val: u8) -> bool {
-    (val & 0x80 == 0) | (val & 0xc0 != 0x80)
+
+// turns bytes into characters as they come in, keeping hold of one that's
+// only partly read. Anything that isn't UTF-8 comes out as U+FFFD, one for
+// each lead byte or stray continuation byte
+#[derive(Default)]
+struct Datgodiwr {
+    buff: [u8; 4],
+    len: usize,
+    eisiau: usize,
+    // a character waiting to be handed out, when one byte finished two
+    nesaf: Option<char>,
 }
-pub fn utf8_len(val: u8) -> usize {
-    // returns the length of the utf8 encoded codepoint
-    // returns 0 if the character is a continuation
-    if val & 0x80 == 0x00 {
-        return 1;
+
+impl Datgodiwr {
+    // takes the next byte, returning a character if that finished one
+    fn bwydo(&mut self, byte: u8) -> Option<char> {
+        if self.len == 0 {
+            return self.dechrau(byte);
+        }
+        // the second byte has a narrower range after some lead bytes, to rule
+        // out overlong encodings, surrogates and anything past U+10FFFF
+        let range = match (self.len, self.buff[0]) {
+            (1, 0xe0) => 0xa0..=0xbf,
+            (1, 0xed) => 0x80..=0x9f,
+            (1, 0xf0) => 0x90..=0xbf,
+            (1, 0xf4) => 0x80..=0x8f,
+            _ => 0x80..=0xbf,
+        };
+        if !range.contains(&byte) {
+            // cut short, what there was is replaced and this byte starts over
+            self.len = 0;
+            self.nesaf = self.dechrau(byte);
+            return Some(char::REPLACEMENT_CHARACTER);
+        }
+        self.buff[self.len] = byte;
+        self.len += 1;
+        if self.len < self.eisiau {
+            return None;
+        }
+        let len = std::mem::take(&mut self.len);
+        std::str::from_utf8(&self.buff[..len]).ok().and_then(|x| x.chars().next()).or(Some(char::REPLACEMENT_CHARACTER))
     }
-    if val & 0xc0 == 0x80 {
-        return 0;
+    fn dechrau(&mut self, byte: u8) -> Option<char> {
+        self.eisiau = match byte {
+            0x00..=0x7f => return Some(byte as char),
+            0xc2..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf4 => 4,
+            // continuation bytes and lead bytes that can't start anything
+            _ => return Some(char::REPLACEMENT_CHARACTER),
+        };
+        self.buff[0] = byte;
+        self.len = 1;
+        None
     }
-    assert!(val & 0xc0 == 0xc0);
-    // here's the annoying
-    let mut ones = 0;
-    for i in (3..=7).rev() {
-        if (val & (1 << i)) == 0 {
-            break;
-        }
-        ones += 1;
+    // the input ended, a partial character is replaced
+    fn gorffen(&mut self) -> Option<char> {
+        (std::mem::take(&mut self.len) > 0).then_some(char::REPLACEMENT_CHARACTER)
     }
-    ones
 }
 pub const VMIN: usize = 6;
+pub const VTIME: usize = 5;
 
 const ICANON: u32 = 0o000002;
 const ECHO: u32 = 0o000010;
 const TCSANOW: i32 = 0;
+const TIOCGWINSZ: u64 = 0x5413;
+const SIGWINCH: i32 = 28;
+const SIG_ERR: usize = usize::MAX;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // what follows the ESC, a `|` being a pause
+    fn key(after: &str) -> Option<(Key, Mods)> {
+        let mut chars = after.chars();
+        dilyniant(|| Ok(chars.next().filter(|&c| c != '|'))).unwrap()
+    }
+
+    fn decode(bytes: &[u8]) -> String {
+        let mut datgodiwr = Datgodiwr::default();
+        let mut out = String::new();
+        for &b in bytes {
+            out.extend(datgodiwr.bwydo(b));
+            out.extend(datgodiwr.nesaf.take());
+        }
+        out.extend(datgodiwr.gorffen());
+        out
+    }
+
+    #[test]
+    fn utf8() {
+        for text in ["a", "ŵ", "€", "🦀", "aŵ€🦀z"] {
+            assert_eq!(decode(text.as_bytes()), text);
+        }
+    }
+
+    #[test]
+    fn bad_utf8() {
+        // stray continuation bytes and impossible lead bytes
+        assert_eq!(decode(b"a\x80b\xffc\xc0"), "a\u{fffd}b\u{fffd}c\u{fffd}");
+        // cut short by the next character, or by the end
+        assert_eq!(decode(b"\xe2\x82a\xf0\x9f\xa6"), "\u{fffd}a\u{fffd}");
+        // overlong, a surrogate and past U+10FFFF
+        assert_eq!(decode(b"\xe0\x80\x80"), "\u{fffd}\u{fffd}\u{fffd}");
+        assert_eq!(decode(b"\xed\xa0\x80"), "\u{fffd}\u{fffd}\u{fffd}");
+        assert_eq!(decode(b"\xf4\x90\x80\x80"), "\u{fffd}\u{fffd}\u{fffd}\u{fffd}");
+        // a new lead byte in the middle of a character
+        assert_eq!(decode(b"\xc5\xe2\x82\xac"), "\u{fffd}€");
+    }
+
+    #[test]
+    fn sequences() {
+        assert_eq!(key(""), Some((Key::Escape, Mods::NONE)));
+        assert_eq!(key("|[A"), Some((Key::Escape, Mods::NONE)));
+        assert_eq!(key("[A"), Some((Key::Up, Mods::NONE)));
+        assert_eq!(key("OD"), Some((Key::Left, Mods::NONE)));
+        assert_eq!(key("[1;5C"), Some((Key::Right, Mods { ctrl: true, ..Mods::NONE })));
+        assert_eq!(key("[1;2B"), Some((Key::Down, Mods { shift: true, ..Mods::NONE })));
+        assert_eq!(key("[5~"), Some((Key::PageUp, Mods::NONE)));
+        assert_eq!(key("[3;3~"), Some((Key::Delete, Mods { alt: true, ..Mods::NONE })));
+        assert_eq!(key("OQ"), Some((Key::F(2), Mods::NONE)));
+        assert_eq!(key("[24~"), Some((Key::F(12), Mods::NONE)));
+        assert_eq!(key("x"), Some((Key::Char('x'), Mods::ALT)));
+        assert_eq!(key("["), Some((Key::Char('['), Mods::ALT)));
+        assert_eq!(key("[99~"), None);
+        assert_eq!(key("[1|"), None);
+    }
+}
@@ -1,353 +1,351 @@
 use std::io::{stdin, stdout, Result};
-use std::io::{Read, BufRead, Write, Error, ErrorKind};
-use std::mem::{size_of};
+use std::io::{Read, Write, Error};
 use std::os::fd::AsRawFd;
-use std::fs::File;
+use std::path::Path;
+use std::time::Instant;
 
-#[derive(Clone)]
-enum TodoItem {
-    Task(bool, String),
-    Group(String, Vec<TodoItem>),
-}
-impl TodoItem {
-    fn completed(&self) -> bool {
-        match self {
-            TodoItem::Task(c, _) => *c,
-            TodoItem::Group(_, xs) => {
-                let mut _bv = true;
-                for x in xs {
-                    _bv &= x.completed();
-                }
-                _bv
-            }
+use nikgwner::history::History;
+use nikgwner::registers::Registers;
+use nikgwner::{markdown, storage};
+use nikgwner::{CursMove, Progress, Selection, TodoItem};
+
+mod args;
+mod llywterf;
+mod paths;
+mod prompt;
+mod view;
+
+use llywterf::{Digwyddiad, Key, Mods};
+use prompt::{Kind, Prompts};
+
+fn main() -> Result<()> {
+    let opts = match args::parse(std::env::args().skip(1)) {
+        Ok(args::Command::Run(opts)) => opts,
+        Ok(args::Command::Help) => {
+            print!("{}", args::USAGE);
+            return Ok(());
         }
-    }
-    fn complete(&mut self, value: bool) {
-        match self {
-            TodoItem::Task(_, msg) => {
-                *self = TodoItem::Task(value, msg.to_string());
-            }
-            TodoItem::Group(_, xs) => {
-                for x in xs.iter_mut() {
-                    x.complete(value)
-                }
-            }
+        Ok(args::Command::Version) => {
+            println!("nikgwner {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
         }
-    }
-    fn message(&self) -> &str {
-        match self {
-            TodoItem::Task(_, msg) => msg,
-            TodoItem::Group(msg, _) => msg,
+        Ok(args::Command::Export(from, to)) => {
+            let list = storage::load(&from).unwrap_or_else(|e| fail(&from, e));
+            return match to {
+                Some(to) => storage::export(&list, &to).or_else(|e| fail(&to, e)),
+                None => markdown::arbed_md(&list, &mut stdout()),
+            };
         }
-    }
-    fn render<W: Write>(&self, depth: u8, outp: &mut W, sel: Option<&Selection>) -> Result<()> {
-        self.render_depth(depth, outp, sel.and_then(|sel| Some((sel, 0))))
-    }
-    fn render_depth<W: Write>(
-        &self,
-        depth: u8,
-        outp: &mut W,
-        sel: Option<(&Selection, usize)>,
-    ) -> Result<()> {
-        let selected = sel.is_some() && {
-            let (s, i) = sel.unwrap();
-            i == s.0.len()
-        };
-        let msg = self.message();
-        let mut out = String::with_capacity(depth as usize + 5 + msg.len());
-        if selected {
-            out.extend("\x1b[7m".chars());
+        Ok(args::Command::Import(from, to)) => {
+            let list = storage::import(&from).unwrap_or_else(|e| fail(&from, e));
+            return storage::save(&list, &to, storage::DEFAULT_BACKUPS).or_else(|e| fail(&to, e));
         }
-        for _ in 0..depth {
-            out.push('\t');
+        Err(e) => {
+            eprint!("nikgwner: {e}\n{}", args::USAGE);
+            std::process::exit(2);
         }
+    };
+    let mut path = opts.path;
+    let mut test = match &path {
+        Some(path) => storage::open(path).unwrap_or_else(|e| fail(path, e)),
+        None => TodoItem::Group(String::from("todo"), vec![], false),
+    };
 
-        if self.completed() {
-            out.extend("[#] ".chars());
-        } else {
-            out.extend("[ ] ".chars());
-        }
-        out.extend(msg.chars());
-        out.push('\n');
-        outp.write(out.as_bytes())?;
-        if let TodoItem::Group(_, xs) = self {
-            for (i, x) in xs.into_iter().enumerate() {
-                let fsel = if selected {
-                    None
-                } else {
-                    sel.and_then(|sel| {
-                        if sel.0.0[sel.1] as usize == i {
-                            Some((sel.0, sel.1 + 1))
-                        } else {
-                            None
-                        }
-                    })
-                };
-                x.render_depth(depth + 1, outp, fsel)?;
-            }
-        }
-        if selected {
-            outp.write("\x1b[0m".as_bytes())?;
-        }
-        Ok(())
+    println!("creating llywterf instance");
+    let mut terf = llywterf::TerfLleol::newidd(stdout(), stdin().lock())?;
+    println!("setting llywterf");
+    match opts.autosave {
+        // wake up every second or so to check the timer
+        args::Autosave::Every(_) => terf.newid().canon(false).echo(false).stopi(false).amser(10).llawnsgrin(true).atod()?,
+        _ => terf.newid().canon(false).echo(false).stopi(true).llawnsgrin(true).atod()?,
     }
-    fn get(&self, sel: &Selection) -> Option<&Self> {
-        let mut cur = self;
-        for i in &(sel.0) {
-            match cur {
-                TodoItem::Task(_, _) => {
-                    return None;
-                }
-                TodoItem::Group(_, xs) => match xs.get(*i as usize) {
-                    Some(x) => cur = x,
-                    None => {
-                        return None;
-                    }
-                },
-            }
-        }
-        Some(cur)
+    terf.gwrando_maint()?;
+    println!("Continuing");
+
+    let mut sel = Selection(vec![]);
+    let mut dirty = false;
+    let mut last_save = Instant::now();
+    // shown on the top line until the next key
+    let mut message: Option<String> = None;
+    let mut history = History::new(opts.undo_depth);
+    let mut registers = Registers::new();
+    // set by `"x`, for the next cut, copy or paste
+    let mut register: Option<char> = None;
+    let mut view = view::View::new();
+    let mut prompts = Prompts::new();
+    let mut recent = paths::Recent::load();
+    if let Some(path) = &path {
+        recent.add(path);
     }
-    fn get_mut(&mut self, sel: &Selection) -> Option<&mut Self> {
-        let mut cur = self;
-        for i in &(sel.0) {
-            match cur {
-                TodoItem::Task(_, _) => {
-                    return None;
+    prompts.set_recent(recent.paths());
+
+    loop {
+        if let (true, Some(targ)) = (dirty, &path) {
+            let due = match opts.autosave {
+                args::Autosave::Off => false,
+                args::Autosave::OnChange => true,
+                args::Autosave::Every(every) => last_save.elapsed() >= every,
+            };
+            if due {
+                match storage::save(&test, targ, opts.backups) {
+                    Ok(()) => dirty = false,
+                    Err(e) => message = Some(format!("couldn't autosave to {}: {e}", targ.display())),
                 }
-                TodoItem::Group(_, xs) => match xs.get_mut(*i as usize) {
-                    Some(x) => cur = x,
-                    None => {
-                        return None;
-                    }
-                },
+                last_save = Instant::now();
             }
         }
-        Some(cur)
-    }
-    fn get_prior(&self, sel: &Selection) -> Option<&Self> {
-        if sel.0.len() == 0 {return Some(self);}
-        let mut cur = self;
-        for i in 0 .. sel.0.len() - 1 {
-            let i = sel.0[i];
-            match cur {
-                TodoItem::Task(_, _) => {
-                    return None;
+        // not a terminal we can ask, go with the classic size
+        let (rows, cols) = terf.maint().unwrap_or((24, 80));
+        let (rows, cols) = (rows as usize, cols as usize);
+        // messages and prompts on the top line, the status bar on the bottom
+        // one and the list in between
+        let top: String = message.as_deref().unwrap_or("").chars().take(cols).collect();
+        print!("\x1b[2J\x1b[1;1H{top}");
+        let page = rows.saturating_sub(2);
+        view.draw(&mut stdout(), &test.lines(Some(&sel), opts.bars), 1, page, cols)?;
+        let mode = match (opts.read_only, register) {
+            (true, _) => String::from("read only"),
+            (false, Some(reg)) => format!("\"{reg}"),
+            (false, None) => String::from("edit"),
+        };
+        print!("\x1b[{rows};1H\x1b[7m{}\x1b[0m", status(path.as_deref(), dirty, test.progress(), &mode, cols));
+        stdout().flush()?;
+        let lth = match terf.aros()? {
+            Some(Digwyddiad::Bysell(key, mods)) => match key_char(key, mods) {
+                Some(lth) => lth,
+                None => continue,
+            },
+            // redraw at the new size
+            Some(Digwyddiad::Maint) => continue,
+            // the autosave timer woke us up
+            Some(Digwyddiad::Amser) => continue,
+            None => break,
+        };
+        message = None;
+        let reg = register.take();
+        match lth {
+            'q' if !dirty || confirm(&mut terf, "unsaved changes, quit anyway? (y/n)")? => break,
+            ' ' | 'i' | 'a' | 'o' | 'O' | 'e' | '{' | '}' | '<' | '>' | 'w' | 'd' | 'p' | 'P' | 'R' | 'X' | 'u' | '\x12' if opts.read_only => (),
+            ' ' if test.get(&sel).is_some() => {
+                let before = test.clone();
+                test.get_mut(&sel).unwrap().toggle(opts.partial_toggle);
+                // a group with no tasks in it doesn't change
+                if test != before {
+                    history.record(&before, &sel);
+                    dirty = true;
                 }
-                TodoItem::Group(_, xs) => match xs.get(i as usize) {
-                    Some(x) => cur = x,
-                    None => {
-                        return None;
-                    }
-                },
             }
-        }
-        Some(cur)
-    }
-    fn get_prior_mut(&mut self, sel: &Selection) -> Option<&mut Self> {
-        let mut cur = self;
-        for i in 0..sel.0.len() - 1 {
-            let i = sel.0[i];
-            match cur {
-                TodoItem::Task(_, _) => {
-                    return None;
-                }
-                TodoItem::Group(_, xs) => match xs.get_mut(i as usize) {
-                    Some(x) => cur = x,
-                    None => {
-                        return None;
+            'u' => if history.undo(&mut test, &mut sel) {
+                dirty = true;
+            } else {
+                message = Some(String::from("nothing to undo"));
+            },
+            // ctrl-r
+            '\x12' => if history.redo(&mut test, &mut sel) {
+                dirty = true;
+            } else {
+                message = Some(String::from("nothing to redo"));
+            },
+            // folds: za or tab toggles the one the cursor is in, zo and zc
+            // open and close it, zR and zM open and close everything
+            'z' | '\t' => 'round: {
+                let key = if lth == '\t' { 'a' } else {
+                    match wait_key(&mut terf)? {
+                        Some(Key::Char(c)) => c,
+                        _ => break 'round,
                     }
-                },
-            }
-        }
-        Some(cur)
-    }
-    fn bound(&self, sel: &Selection) -> bool {
-        self.get(sel).is_some()
-    }
-    fn insert(&mut self, value: Self) {
-        match self {
-            TodoItem::Group(_, xs) => xs.push(value),
-            TodoItem::Task(_, msg) => {
-                *self = TodoItem::Group(msg.to_string(), vec![value]);
-            }
-        }
-    }
-    fn is_group(&self) -> bool {
-        match self {
-            TodoItem::Group(_, _) => true,
-            _ => false,
-        }
-    }
-    fn check_move(&self, sel: &Selection, action: CursMove) -> bool {
-        match action {
-            CursMove::Down => self.get_prior(sel).and_then(|x| {
-                match x {
-                    TodoItem::Task(_, _) => None,
-                    TodoItem::Group(_, xs) => {
-                        if sel.0.len() == 0 { return None; }
-                        let prior_ind = sel.0[sel.0.len() - 1] as usize;
-                        if prior_ind + 1 < xs.len() {Some(())} else {None}
+                };
+                let before = test.clone();
+                match key {
+                    'R' | 'M' => {
+                        test.fold(false);
+                        test.fold_all(key == 'M');
+                        test.surface(&mut sel);
+                    }
+                    'a' | 'o' | 'c' => {
+                        // on a task, fold the group it's in. Not the root
+                        // though, that would hide the whole list
+                        let mut target = sel.clone();
+                        if test.get(&target).is_some_and(|x| !x.is_group()) {
+                            target.do_move(CursMove::Out);
+                        }
+                        if target.0.is_empty() {
+                            break 'round;
+                        }
+                        let Some(x) = test.get_mut(&target) else { break 'round; };
+                        let value = match key {
+                            'a' => !x.folded(),
+                            _ => key == 'c',
+                        };
+                        x.fold(value);
+                        sel = target;
                     }
+                    _ => break 'round,
                 }
-            }).is_some(),
-            CursMove::Up   => self.get_prior(sel).and_then(|x| {
-                if sel.0.len() == 0 { return None; }
-                let prior_ind = sel.0[sel.0.len() - 1] as usize;
-                if prior_ind > 0 {Some(())} else {None}
-            }).is_some(),
-            CursMove::Out  => self.get_prior(sel).is_some(),
-            CursMove::In   => self.get(sel)
-                                  .and_then(|x|if x.is_group() {Some(())} else {None})
-                                  .is_some(),
-        }
-    }
-    fn do_move(&self, sel: &mut Selection, action: CursMove) {
-        if self.check_move(sel, action) {
-            sel.do_move(action);
-        }
-    }
-
-    fn delete(&mut self, sel: &Selection) -> Option<()> {
-        let prev = self.get_prior_mut(sel)?;
-        if let TodoItem::Group(_,xs) = prev {
-            xs.remove(sel.get_end()? as usize);
-        }
-        Some(())
-    }
-}
-
-#[derive(Clone)]
-
-struct Selection(Vec<u8>);
-// selected is `depth == Selection.0.len()`
-impl Selection {
-    fn do_move(&mut self, action: CursMove) {
-        // Assumes that it can move
-        let clen = self.0.len();
-        match action {
-            CursMove::Down => self.0[ clen - 1] += 1,
-            CursMove::Up => self.0[clen - 1] -= 1,
-            CursMove::Out => {self.0.pop();},
-            CursMove::In => self.0.push(0),
-        }
-    }
-    fn get_end(&self) -> Option<u8> {
-        self.0.get(self.0.len() - 1).copied()
-    }
-}
-
-#[derive(Debug, Clone, Copy)]
-enum CursMove {
-    In,
-    Out,
-    Down,
-    Up,
-}
-
-mod llywterf;
-fn main() -> Result<()> {
-    println!("creating llywterf instance");
-    let mut terf = llywterf::TerfLleol::newidd(stdout(), stdin().lock())?;
-    println!("setting llywterf");
-    terf.newid().canon(false).echo(false).llawnsgrin(true).atod()?;
-    println!("Continuing");
-
-    let mut test = TodoItem::Group(
-        String::from("test 1"),
-        vec![
-            TodoItem::Group(String::from("test 1.1"), vec![
-                            TodoItem::Task(false, String::from("test 1.1.1")),
-                            TodoItem::Task(false, String::from("test 1.1.2")),
-                        ]),
-            TodoItem::Task(false, String::from("test 1.2")),
-            TodoItem::Group(String::from("test 1.3"), vec![
-                            TodoItem::Task(false, String::from("test 1.3.1")),
-                            TodoItem::Task(false, String::from("test 1.3.2")),
-                        ]),
-        ],
-    );
-
-    let mut sel = Selection(vec![]);
-
-    /*
-    'testo: {
-        
-        println!("Testing arbed and lwytho");
-        let mut buff = Vec::<u8>::with_capacity(32);
-        test.arbed(&mut buff)?;
-        println!("llwytho object");
-        let out = TodoItem::llwytho(&mut VecRead::new(buff))?;
-        out.render(1, &mut stdout(), None)?;
-        
-        // todo!("Re-start saving tests");
-        terf.ungell()?;
-    }
-    */
-    
-    loop {
-        println!("\x1b[2J\x1b[1;1H");
-        test.render(1, &mut stdout(), Some(&sel))?;
-        let lth = terf.ungell()?;
-        if lth.is_none() {break;}
-        match lth.unwrap() {
-            'q' => break,
-            ' ' => {test.get_mut(&sel).map(|x| x.complete(!x.completed()));},
+                // folds are saved with the list
+                if test != before {
+                    dirty |= !opts.read_only;
+                }
+            }
             'h' => test.do_move(&mut sel, CursMove::Out),
             'l' => test.do_move(&mut sel, CursMove::In),
             'j' => test.do_move(&mut sel, CursMove::Down),
             'k' => test.do_move(&mut sel, CursMove::Up),
-            'J' => 'round: {
-                // in, down, out'n'down
-                if test.check_move(&sel, CursMove::In)   {sel.do_move(CursMove::In);   break 'round;}
-                if test.check_move(&sel, CursMove::Down) {sel.do_move(CursMove::Down); break 'round;}
-                if test.check_move(&sel, CursMove::Out)  {
-                    let save = Selection(sel.0.clone());
-                    sel.do_move(CursMove::Out);
-                    if !test.check_move(&sel, CursMove::Down) {
-                        sel = save;
-                        break 'round;
-                    }
-                    sel.do_move(CursMove::Down);
-                    break 'round;
+            // to the top and bottom, and a page down or up with ctrl-f and ctrl-b
+            'g' | 'G' | '\x06' | '\x02' => {
+                let lines = test.lines(Some(&sel), opts.bars);
+                let cursor = lines.iter().position(|x| x.cursor).unwrap_or(0);
+                let to = match lth {
+                    'g' => 0,
+                    'G' => lines.len() - 1,
+                    '\x06' => (cursor + page).min(lines.len() - 1),
+                    _ => cursor.saturating_sub(page),
+                };
+                sel = lines[to].path.clone();
+            }
+            'J' => test.move_next(&mut sel),
+            'K' => test.move_prev(&mut sel),
+            // move the item itself: up, down, into the one above, out of its group
+            '{' | '}' | '>' | '<' => {
+                let action = match lth {
+                    '{' => CursMove::Up,
+                    '}' => CursMove::Down,
+                    '>' => CursMove::In,
+                    _ => CursMove::Out,
+                };
+                let before = (test.clone(), sel.clone());
+                if test.shift(&mut sel, action) {
+                    history.record(&before.0, &before.1);
+                    // `>` may have moved it into a folded group
+                    test.reveal(&sel);
+                    dirty = true;
                 }
             }
-            'K' => 'round: {
-                // out, up 
-                if test.check_move(&sel, CursMove::Up) {
-                    sel.do_move(CursMove::Up);
-                    while test.check_move(&sel, CursMove::In) {
-                        sel.do_move(CursMove::In);
-                        while test.check_move(&sel, CursMove::Down) {
-                            sel.do_move(CursMove::Down);
-                        }
-                    }
+            'i' => 'round: {
+                let Some(item) = prompts.ask(&mut terf, Kind::Item, "new item: ", "")? else { break 'round; };
+                if !item.is_empty() && test.get(&sel).is_some() {
+                    history.record(&test, &sel);
+                    test.get_mut(&sel).unwrap().insert(TodoItem::Task(false, item));
+                    dirty = true;
+                }
+            }
+            // a new child, or a new sibling below or above, and select it
+            'a' | 'o' | 'O' => 'round: {
+                if test.get(&sel).is_none() {
+                    break 'round;
+                }
+                let Some(msg) = prompts.ask(&mut terf, Kind::Item, "new item: ", "")? else { break 'round; };
+                if msg.is_empty() {
                     break 'round;
                 }
-                if test.check_move(&sel, CursMove::Out) {sel.do_move(CursMove::Out); break 'round;} 
+                history.record(&test, &sel);
+                let item = TodoItem::Task(false, msg);
+                if lth == 'a' {
+                    test.add_child(&mut sel, item);
+                    // it may have gone into a folded group
+                    test.reveal(&sel);
+                } else {
+                    test.insert_sibling(&mut sel, item, lth == 'o');
+                }
+                dirty = true;
             }
-            'i' => {
-                let item = prompt(&mut terf)?;
-                test.get_mut(&sel).map( move |x|x.insert(TodoItem::Task(false, item)));
-                terf.newid().echo(false).canon(false).atod()?;
-                /* get input, trim, insert */
+            'e' => 'round: {
+                let Some(x) = test.get(&sel) else { break 'round; };
+                let Some(msg) = prompts.ask(&mut terf, Kind::Item, "edit: ", x.message())? else { break 'round; };
+                if msg != x.message() {
+                    history.record(&test, &sel);
+                    test.get_mut(&sel).unwrap().set_message(msg);
+                    dirty = true;
+                }
             }
-            'w' => {
-                let mut file = File::create(prompt(&mut terf)?)?;
-                test.arbed(&mut file)?;
+            'w' => 'round: {
+                let targ = match &path {
+                    Some(targ) => targ.clone(),
+                    None => match prompts.ask(&mut terf, Kind::Path, "save as: ", "")? {
+                        Some(targ) => paths::expand(&targ),
+                        None => break 'round,
+                    },
+                };
+                if let Err(e) = storage::save(&test, &targ, opts.backups) {
+                    message = Some(format!("couldn't save to {}: {e}", targ.display()));
+                    break 'round;
+                }
+                message = Some(format!("saved to {}", targ.display()));
+                recent.add(&targ);
+                prompts.set_recent(recent.paths());
+                path = Some(targ);
+                dirty = false;
+                last_save = Instant::now();
             }
-            'W' => {
-                let mut file = File::open(prompt(&mut terf)?)?;
-                match TodoItem::llwytho(&mut file) {
-                    Ok(nxt) => { test = nxt;  }
-                    Err(e)  => {
-                        terf.write(b"\x1b[H\x1b[2K\x1b[0m> ")?;
-                        write!(terf, "{e}")?;
+            'W' => 'round: {
+                if dirty && !confirm(&mut terf, "unsaved changes, open another list anyway? (y/n)")? {
+                    break 'round;
+                }
+                let Some(targ) = prompts.ask(&mut terf, Kind::Path, "open: ", "")? else { break 'round; };
+                let targ = paths::expand(&targ);
+                match storage::load(&targ) {
+                    Ok(nxt) => {
+                        test = nxt; sel = Selection(vec![]); dirty = false; history.clear();
+                        recent.add(&targ);
+                        prompts.set_recent(recent.paths());
+                        path = Some(targ);
                     }
+                    Err(e)  => message = Some(format!("couldn't load {}: {e}", targ.display())),
+                }
+            }
+            'x' => 'round: {
+                let Some(targ) = prompts.ask(&mut terf, Kind::Path, "export to: ", "")? else { break 'round; };
+                let targ = paths::expand(&targ);
+                if let Err(e) = storage::export(&test, &targ) {
+                    message = Some(format!("couldn't export to {}: {e}", targ.display()));
                 }
             }
-            'd' => {
-                 test.delete(&sel);
+            'X' => 'round: {
+                // replaces the list, but it still saves to the same place
+                let Some(targ) = prompts.ask(&mut terf, Kind::Path, "import: ", "")? else { break 'round; };
+                let targ = paths::expand(&targ);
+                match storage::import(&targ) {
+                    Ok(nxt) => { history.record(&test, &sel); test = nxt; sel = Selection(vec![]); dirty = true; }
+                    Err(e)  => message = Some(format!("couldn't import {}: {e}", targ.display())),
+                }
+            }
+            'R' => 'round: {
+                // pull an older version back in, it only replaces the file once saved
+                let Some(targ) = &path else { break 'round; };
+                let Some(n) = prompts.ask(&mut terf, Kind::Backup, "restore backup (1): ", "")? else { break 'round; };
+                let n = if n.is_empty() { Ok(1) } else { n.trim().parse::<usize>() };
+                let Ok(n) = n else { break 'round; };
+                match storage::restore(targ, n) {
+                    Ok(nxt) => { history.record(&test, &sel); test = nxt; sel = Selection(vec![]); dirty = true; }
+                    Err(e)  => message = Some(format!("couldn't restore backup {n}: {e}")),
+                }
+            }
+            '"' => match wait_key(&mut terf)? {
+                Some(Key::Char(c)) if Registers::valid(c) => register = Some(c),
+                Some(Key::Char(c)) => message = Some(format!("no register {c}")),
+                _ => (),
+            },
+            'y' => if let Some(x) = test.get(&sel) {
+                registers.set(reg, x.clone());
+            },
+            'p' | 'P' => match registers.get(reg) {
+                Some(x) if test.get(&sel).is_some() => {
+                    history.record(&test, &sel);
+                    test.insert_sibling(&mut sel, x.clone(), lth == 'p');
+                    dirty = true;
+                }
+                Some(_) => (),
+                None => message = Some(format!("register {} is empty", reg.unwrap_or('"'))),
+            },
+            'd' if test.get(&sel).is_some() && !sel.0.is_empty() => {
+                history.record(&test, &sel);
+                registers.set(reg, test.delete(&sel).unwrap());
+                dirty = true;
+                // keep the cursor on something that's still there
+                if test.get(&sel).is_none() {
+                    let action = if sel.get_end() > Some(0) { CursMove::Up } else { CursMove::Out };
+                    sel.do_move(action);
+                }
             }
             _ => (),
         }
@@ -355,129 +353,65 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn prompt<O: Write + AsRawFd, I: Read + BufRead + AsRawFd>(terf: &mut llywterf::TerfLleol<O, I> ) -> Result<String> {
-    terf.write(b"\x1b[H\x1b[2K\x1b[0m> ")?;
-    terf.flush()?;
-    let mut buff = String::with_capacity(16);
-    terf.newid().echo(true).canon(true).atod()?;
-    let l = terf.read_line(&mut buff)?;
-    buff.truncate(l - 1);
-    terf.newid().echo(false).canon(false).atod()?;
-    return Ok(buff);
+fn fail<T>(path: &Path, e: Error) -> T {
+    eprintln!("nikgwner: {}: {e}", path.display());
+    std::process::exit(1);
 }
 
-
-trait Arbed where Self: Sized {
-    fn arbed<W: Write>(&self, allbwn: &mut W) -> Result<()>;
-    fn llwytho<R: Read>(mewnbwn: &mut R) -> Result<Self>;
-}
-impl Arbed for u8 {
-    fn arbed<W: Write>(&self, allbwn: &mut W) -> Result<()> {
-        allbwn.write(&self.to_le_bytes()).and(Ok(()))
-    }
-    fn llwytho< R: Read>(mewnbwn: &mut R) -> Result<Self> {
-        let mut buff: [u8; size_of::<Self>()] = [0; size_of::<Self>()];
-        mewnbwn.read_exact(&mut buff)?;
-        Ok(Self::from_le_bytes(buff))
-    }
-}
-impl Arbed for u16 {
-    fn arbed<W: Write>(&self, allbwn: &mut W) -> Result<()> {
-        allbwn.write(&self.to_le_bytes()).and(Ok(()))
-    }
-    fn llwytho< R: Read>(mewnbwn: &mut R) -> Result<Self> {
-        let mut buff: [u8; size_of::<Self>()] = [0; size_of::<Self>()];
-        mewnbwn.read_exact(&mut buff)?;
-        Ok(Self::from_le_bytes(buff))
-    }
-}
-impl Arbed for bool {
-    fn arbed<W: Write>(&self, allbwn:&mut W) -> Result<()> {
-        let buff = (if *self {1} else {0} as u8).to_le_bytes();
-        allbwn.write(&buff)?;
-        Ok(())
-    }
-    fn llwytho<R: Read>(mewnbwn: &mut R) -> Result<Self> {
-        let mut buff: [u8; 1] = [ 0 ];
-        mewnbwn.read_exact(&mut buff)?;
-        Ok(u8::from_le_bytes(buff) != 0)
-    }
-}
-impl<T: Arbed> Arbed for Vec<T> {
-    fn arbed<W: Write>(&self, allbwn: &mut W) -> Result<()> {
-        (self.len() as u8).arbed(allbwn);
-        for i in self { i.arbed(allbwn); }
-        Ok(())
-    }
-    fn llwytho<R: Read>(mewnbwn: &mut R) -> Result<Self> {
-        let mut temp = Vec::with_capacity(u8::llwytho(mewnbwn)? as usize);
-        for _ in 0 .. temp.capacity() {
-            temp.push(T::llwytho(mewnbwn)?)
-        }
-        Ok(temp)
-    } 
+// Asks a yes/no question on the message line
+fn confirm<O: Write + AsRawFd, I: Read + AsRawFd>(terf: &mut llywterf::TerfLleol<O, I>, question: &str) -> Result<bool> {
+    write!(terf, "\x1b[H\x1b[2K\x1b[0m{question} ")?;
+    terf.flush()?;
+    Ok(matches!(wait_key(terf)?, Some(Key::Char('y' | 'Y'))))
 }
 
-impl Arbed for String {
-    fn arbed<W: Write>(&self, allbwn: &mut W) -> Result<()> {
-        (self.len() as u16).arbed(allbwn)?;
-        allbwn.write(self.as_bytes())?;
-        Ok(())
-    }
-    fn llwytho<R: Read>(mewnbwn: &mut R) -> Result<Self> {
-        let mut buff = vec![0u8; u16::llwytho(mewnbwn)? as usize];
-        mewnbwn.read_exact(&mut buff)?;
-        match String::from_utf8(buff) {
-            Ok(msg) => Ok(msg),
-            Err(e)  => Err(Error::new(ErrorKind::Other, e))
-        } 
+// Waits for the next key, through any read timeouts. `None` if the input is gone
+fn wait_key<O: Write + AsRawFd, I: Read + AsRawFd>(terf: &mut llywterf::TerfLleol<O, I>) -> Result<Option<Key>> {
+    loop {
+        match terf.aros()? {
+            Some(Digwyddiad::Bysell(key, _)) => return Ok(Some(key)),
+            Some(_) => continue,
+            None => return Ok(None),
+        }
     }
 }
 
-impl Arbed for TodoItem {
-    fn arbed<W: Write>(&self, allbwn: &mut W) -> Result<()> {
-        match self {
-            TodoItem::Task(c, msg) => {
-                u8::arbed(&0, allbwn)?;
-                c.arbed(allbwn)?;
-                msg.arbed(allbwn)?;
-            }
-            TodoItem::Group(msg, xs) => {
-                u8::arbed(&1, allbwn)?;
-                msg.arbed(allbwn)?;
-                xs.arbed(allbwn)?;
-            }
-        }
-        Ok(())
-    }
-    fn llwytho<R: Read>(mewnbwn: &mut R) -> Result<Self> {
-        match u8::llwytho(mewnbwn)? {
-            0 => {
-                Ok(TodoItem::Task(bool::llwytho(mewnbwn)?, String::llwytho(mewnbwn)?))
-            }
-            1 => {
-                Ok(TodoItem::Group(String::llwytho(mewnbwn)?, Vec::<TodoItem>::llwytho(mewnbwn)?))
-            }
-            x => panic!("couldn't llwytho TodoItem of id {x}"),
-        }
-    }
+// The key commands are letters, the arrow keys and friends stand in for
+// the ones that do the same thing. `None` for keys that do nothing
+fn key_char(key: Key, mods: Mods) -> Option<char> {
+    let c = match (key, mods.shift) {
+        (Key::Char(c), _) if mods == Mods::NONE => c,
+        // to the line above and below, or moving the item with shift
+        (Key::Up, false) => 'K',
+        (Key::Down, false) => 'J',
+        (Key::Left, false) => 'h',
+        (Key::Right, false) => 'l',
+        (Key::Up, true) => '{',
+        (Key::Down, true) => '}',
+        (Key::Left, true) => '<',
+        (Key::Right, true) => '>',
+        (Key::Home, _) => 'g',
+        (Key::End, _) => 'G',
+        (Key::PageUp, _) => '\x02',
+        (Key::PageDown, _) => '\x06',
+        _ => return None,
+    };
+    Some(c)
 }
 
-struct VecRead<T>(Vec<T>, usize);
-impl<T> VecRead<T> { fn new(inp: Vec<T>) -> Self { VecRead(inp, 0) } }
-impl Read for VecRead<u8> {
-    fn read(&mut self, targ: &mut [u8]) -> Result<usize> {
-        if targ.len() <= self.0.len() - self.1 {
-            for i in self.1 .. self.1 + targ.len() {
-                targ[i - self.1] = self.0[i];
-            }
-            self.1 += targ.len();
-            return Ok(targ.len());
-        }
-        let l = self.0.len() - self.1;
-        for i in 0 .. l {
-            targ[i] = self.0[self.1 + i];
-        }
-        Ok(l)
-    } 
+// The bar along the bottom, `width` columns wide: the file and whether it
+// has unsaved changes on the left, how much is done and the mode on the right
+fn status(path: Option<&Path>, dirty: bool, progress: Progress, mode: &str, width: usize) -> String {
+    let mut left = match path {
+        Some(path) => path.display().to_string(),
+        None => String::from("[no file]"),
+    };
+    if dirty {
+        left.push_str(" [+]");
+    }
+    let right = format!("{}/{} done ({}%) | {mode} ", progress.done, progress.total, progress.percent());
+    let left: String = left.chars().take(width.saturating_sub(right.chars().count() + 2)).collect();
+    let gap = width.saturating_sub(left.chars().count() + right.chars().count() + 1);
+    let out = format!(" {left}{}{right}", " ".repeat(gap));
+    out.chars().take(width).collect()
 }
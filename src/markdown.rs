@@ -0,0 +1,167 @@
+//! Lists as GitHub style Markdown task lists, for keeping them in git or
+//! editing them by hand.
+//!
+//! ```text
+//! # root title
+//!
+//! - [ ] a task
+//! - [x] a finished task
+//! - [ ] a group
+//!   - [ ] with a child
+//! ```
+//!
+//! A group is an item with children under it. Its box is written from
+//! `completed`, and ignored on the way back in since the children decide it.
+//! That also means a group with no children comes back as a task.
+//!
+//! Each item is one line, so line breaks in titles are written as spaces.
+
+use std::io::{Error, ErrorKind, Result, Write};
+
+use crate::TodoItem;
+
+const INDENT: &str = "  ";
+
+/// Writes `list` as a Markdown task list under a heading.
+pub fn arbed_md<W: Write>(list: &TodoItem, allbwn: &mut W) -> Result<()> {
+    writeln!(allbwn, "# {}", one_line(list.message()))?;
+    if let TodoItem::Group(_, xs, _) = list {
+        if !xs.is_empty() {
+            writeln!(allbwn)?;
+        }
+        for x in xs {
+            arbed_eitem(x, 0, allbwn)?;
+        }
+    }
+    Ok(())
+}
+
+fn arbed_eitem<W: Write>(item: &TodoItem, depth: usize, allbwn: &mut W) -> Result<()> {
+    let mark = if item.completed() { 'x' } else { ' ' };
+    writeln!(allbwn, "{}- [{mark}] {}", INDENT.repeat(depth), one_line(item.message()))?;
+    if let TodoItem::Group(_, xs, _) = item {
+        for x in xs {
+            arbed_eitem(x, depth + 1, allbwn)?;
+        }
+    }
+    Ok(())
+}
+
+fn one_line(text: &str) -> String {
+    text.replace("\r\n", " ").replace(['\r', '\n'], " ")
+}
+
+/// Parses a Markdown task list. `name` titles the list if the text doesn't
+/// start with a heading.
+pub fn llwytho_md(text: &str, name: &str) -> Result<TodoItem> {
+    let mut root = TodoItem::Group(String::from(name), vec![], false);
+    // indent and selection of each item we could still be nested under
+    let mut open: Vec<(usize, Vec<usize>)> = vec![];
+    let mut seen_item = false;
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(title) = line.strip_prefix("# ") {
+            if seen_item {
+                return Err(line_error(n, "the heading has to come before the list"));
+            }
+            root = TodoItem::Group(title.trim().to_string(), vec![], false);
+            seen_item = true;
+            continue;
+        }
+        let Some((indent, done, title)) = list_item(line) else {
+            return Err(line_error(n, "expected a `- [ ]` list item"));
+        };
+        seen_item = true;
+        while open.last().is_some_and(|(i, _)| *i >= indent) {
+            open.pop();
+        }
+        let mut path = open.last().map_or(vec![], |(_, path)| path.clone());
+        let parent = root.get_mut(&crate::Selection(path.clone())).unwrap();
+        parent.insert(TodoItem::Task(done, title.to_string()));
+        if let TodoItem::Group(_, xs, _) = parent {
+            path.push(xs.len() - 1);
+        }
+        open.push((indent, path));
+    }
+    Ok(root)
+}
+
+// `- [x] title`, with `*` or `+` for the bullet too. The box is optional,
+// a plain item is an unfinished task.
+fn list_item(line: &str) -> Option<(usize, bool, &str)> {
+    let body = line.trim_start();
+    let indent = line[..line.len() - body.len()]
+        .chars()
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum();
+    let body = body.strip_prefix(['-', '*', '+'])?;
+    let body = body.strip_prefix(' ')?.trim_start();
+    let (done, title) = match body.get(..3) {
+        Some("[ ]") => (false, &body[3..]),
+        Some("[x]") | Some("[X]") => (true, &body[3..]),
+        _ => (false, body),
+    };
+    Some((indent, done, title.trim()))
+}
+
+fn line_error(n: usize, reason: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("line {}: {reason}", n + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(done: bool, msg: &str) -> TodoItem {
+        TodoItem::Task(done, String::from(msg))
+    }
+
+    #[test]
+    fn round_trip() {
+        let list = TodoItem::Group(
+            String::from("root"),
+            vec![
+                TodoItem::Group(String::from("a"), vec![task(true, "a.1"), task(false, "a.2")], false),
+                task(true, "b"),
+                TodoItem::Group(String::from("c"), vec![TodoItem::Group(String::from("c.1"), vec![task(false, "c.1.1")], false)], false),
+            ],
+            false,
+        );
+        let mut buff = Vec::new();
+        arbed_md(&list, &mut buff).unwrap();
+        let text = String::from_utf8(buff).unwrap();
+        assert_eq!(llwytho_md(&text, "unused").unwrap(), list);
+    }
+
+    #[test]
+    fn line_breaks() {
+        let list = TodoItem::Group(String::from("r\noot"), vec![task(false, "a\n# b"), task(true, "c\r\nd")], false);
+        let mut buff = Vec::new();
+        arbed_md(&list, &mut buff).unwrap();
+        let text = String::from_utf8(buff).unwrap();
+        let expected = TodoItem::Group(String::from("r oot"), vec![task(false, "a # b"), task(true, "c d")], false);
+        assert_eq!(llwytho_md(&text, "unused").unwrap(), expected);
+    }
+
+    #[test]
+    fn loose_input() {
+        let text = "\t* [X] one\n\t\t+ two\n\n- three\n";
+        let list = llwytho_md(text, "name").unwrap();
+        assert_eq!(
+            list,
+            TodoItem::Group(
+                String::from("name"),
+                vec![TodoItem::Group(String::from("one"), vec![task(false, "two")], false), task(false, "three")],
+                false,
+            ),
+        );
+    }
+
+    #[test]
+    fn stray_text() {
+        let e = llwytho_md("# a\n- [ ] b\nnot an item\n", "a").unwrap_err();
+        assert_eq!(e.to_string(), "line 3: expected a `- [ ]` list item");
+    }
+}
@@ -0,0 +1,141 @@
+// Tab completion of file names in the path prompts, and the list of
+// recently used files that's kept between runs.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// how many recent files are remembered
+const RECENT: usize = 20;
+
+// Every way to finish off the file name at the end of `text`, in order.
+// Directories end in a `/` so completion can carry on into them. Hidden
+// files only show up once a `.` has been typed
+pub fn complete(text: &str) -> Vec<String> {
+    let (dir, prefix) = match text.rfind('/') {
+        Some(i) => text.split_at(i + 1),
+        None => ("", text),
+    };
+    let from = if dir.is_empty() { PathBuf::from(".") } else { expand(dir) };
+    let Ok(entries) = fs::read_dir(from) else { return vec![]; };
+    let mut out: Vec<String> = entries
+        .filter_map(|x| {
+            let x = x.ok()?;
+            let name = x.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            // following links, a link to a directory can be completed into
+            let slash = if x.path().is_dir() { "/" } else { "" };
+            Some(format!("{dir}{name}{slash}"))
+        })
+        .collect();
+    out.sort();
+    out
+}
+
+// The longest start all of `xs` share
+pub fn common_prefix(xs: &[String]) -> String {
+    let Some(first) = xs.first() else { return String::new(); };
+    let mut end = 0;
+    for (i, c) in first.char_indices() {
+        if !xs.iter().all(|x| x.get(i..).is_some_and(|x| x.starts_with(c))) {
+            break;
+        }
+        end = i + c.len_utf8();
+    }
+    first[..end].to_string()
+}
+
+// A path as typed into a prompt, with a leading `~` being home. Used for
+// both completing and opening, so the two always agree
+pub fn expand(text: &str) -> PathBuf {
+    let home = std::env::var_os("HOME");
+    match (text.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            Path::new(&home).join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(text),
+    }
+}
+
+// Files opened and saved, most recent first. Kept in
+// `$XDG_STATE_HOME/nikgwner/recent`, or under `~/.local/state`, one path a
+// line. Failing to read or write it isn't worth bothering anyone over
+pub struct Recent {
+    file: Option<PathBuf>,
+    paths: Vec<String>,
+}
+
+impl Recent {
+    pub fn load() -> Self {
+        let file = std::env::var_os("XDG_STATE_HOME")
+            .filter(|x| !x.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/state")))
+            .map(|dir| dir.join("nikgwner").join("recent"));
+        let paths = file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map_or(vec![], |text| text.lines().filter(|x| !x.is_empty()).map(String::from).take(RECENT).collect());
+        Recent { file, paths }
+    }
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+    // Puts `path` at the top of the list and saves it
+    pub fn add(&mut self, path: &Path) {
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let Some(path) = path.to_str() else { return; };
+        self.paths.retain(|x| x != path);
+        self.paths.insert(0, path.to_string());
+        self.paths.truncate(RECENT);
+        if let Some(file) = &self.file {
+            let mut text = self.paths.join("\n");
+            text.push('\n');
+            let _ = file.parent().map(fs::create_dir_all);
+            let _ = fs::write(file, text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completion() {
+        let dir = std::env::temp_dir().join(format!("nikgwner-complete-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["list.nik", "list.md", ".hidden", "other"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let base = format!("{}/", dir.display());
+        let all = complete(&base);
+        assert_eq!(all, [format!("{base}list.md"), format!("{base}list.nik"), format!("{base}other"), format!("{base}sub/")]);
+        let lists = complete(&format!("{base}li"));
+        assert_eq!(common_prefix(&lists), format!("{base}list."));
+        assert_eq!(complete(&format!("{base}.h")), [format!("{base}.hidden")]);
+        assert!(complete(&format!("{base}nope/")).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn home() {
+        let Some(home) = std::env::var_os("HOME") else { return; };
+        let home = Path::new(&home);
+        assert_eq!(expand("~"), home);
+        assert_eq!(expand("~/lists/a.nik"), home.join("lists/a.nik"));
+        assert_eq!(expand("~other/a.nik"), PathBuf::from("~other/a.nik"));
+        assert_eq!(expand("a/~/b"), PathBuf::from("a/~/b"));
+    }
+
+    #[test]
+    fn prefixes() {
+        let xs = |v: &[&str]| v.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        assert_eq!(common_prefix(&xs(&["abc", "abd"])), "ab");
+        assert_eq!(common_prefix(&xs(&["ŵa", "ŵb"])), "ŵ");
+        assert_eq!(common_prefix(&xs(&["x", "y"])), "");
+        assert_eq!(common_prefix(&xs(&["same"])), "same");
+        assert_eq!(common_prefix(&[]), "");
+    }
+}
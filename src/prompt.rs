@@ -0,0 +1,298 @@
+// the line editor behind every prompt. It runs with the terminal still in
+// raw mode and draws the line itself on the message line, so the cursor can
+// move about in the text. The keys are the usual readline ones:
+//
+//   left, right, ctrl-b, ctrl-f       a character
+//   ctrl-left, ctrl-right, alt-b/f    a word
+//   home, end, ctrl-a, ctrl-e         the start and end
+//   ctrl-k, ctrl-u                    kill to the end or the start
+//   ctrl-w, alt-backspace, alt-d      kill a word back or forward
+//   ctrl-y                            yank back what was killed
+//   up, down, ctrl-p, ctrl-n          earlier and later answers
+//   tab, shift-tab                    complete a path, see below
+//   escape                            give up
+//
+// In the path prompts tab finishes off as much of the file name as it can.
+// If there's more than one way to go the choices are listed under the
+// prompt, and pressing tab again goes through them. On an empty line it
+// lists the recent files instead.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Read, Result, Write};
+use std::os::fd::AsRawFd;
+
+use crate::llywterf::{Digwyddiad, Key, TerfLleol};
+use crate::paths;
+use crate::view::columns;
+
+// answers remembered for each kind of prompt
+const HANES: usize = 100;
+
+// what a prompt is asking for. Each keeps its own history, so paths don't
+// turn up when going back through item titles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Item,
+    Path,
+    Backup,
+}
+
+pub struct Prompts {
+    hanes: HashMap<Kind, Vec<String>>,
+    // the last thing killed, for ctrl-y. Shared by every prompt
+    kill: Vec<char>,
+    // offered by tab on an empty path prompt
+    recent: Vec<String>,
+}
+
+// the choices tab found, under the prompt
+struct Popup {
+    choices: Vec<String>,
+    // the one in the line, once tab has started going through them
+    at: Option<usize>,
+    // recent files are shown whole, completions by their last part
+    whole: bool,
+    // rows drawn last time, to clear after
+    drawn: usize,
+}
+
+impl Prompts {
+    pub fn new() -> Self {
+        Prompts { hanes: HashMap::new(), kill: vec![], recent: vec![] }
+    }
+    pub fn set_recent(&mut self, paths: &[String]) {
+        self.recent = paths.to_vec();
+    }
+    // Asks on the message line after `label`, starting with `initial`.
+    // Returns the text once enter is hit, `None` for escape or if the input
+    // went away first
+    pub fn ask<O: Write + AsRawFd, I: Read + BufRead + AsRawFd>(
+        &mut self,
+        terf: &mut TerfLleol<O, I>,
+        kind: Kind,
+        label: &str,
+        initial: &str,
+    ) -> Result<Option<String>> {
+        let hanes = self.hanes.entry(kind).or_default();
+        // the history with the line being written on the end, edits to old
+        // answers last until the prompt is done with
+        let mut lines: Vec<Vec<char>> = hanes.iter().map(|x| x.chars().collect()).collect();
+        lines.push(initial.chars().collect());
+        let mut at = lines.len() - 1;
+        let mut line = Llinell::new(&lines[at]);
+        let mut popup: Option<Popup> = None;
+        loop {
+            let (height, width) = terf.maint().map_or((24, 80), |(rows, cols)| (rows as usize, cols as usize));
+            if let Some(popup) = &mut popup {
+                popup.draw(terf, height, width)?;
+            }
+            line.draw(terf, label, width)?;
+            let (key, mods) = match terf.aros()? {
+                Some(Digwyddiad::Bysell(key, mods)) => (key, mods),
+                // a read that timed out, or a resize, just draw it again
+                Some(_) => continue,
+                None => return Ok(None),
+            };
+            let tab = key == Key::Char('\t') && kind == Kind::Path;
+            if !tab {
+                if let Some(popup) = popup.take() {
+                    popup.clear(terf)?;
+                }
+            }
+            let word = mods.ctrl || mods.alt;
+            match (key, mods.alt) {
+                // going through the choices, backwards with shift
+                _ if tab && popup.is_some() => {
+                    let popup = popup.as_mut().unwrap();
+                    let len = popup.choices.len();
+                    let next = match (popup.at, mods.shift) {
+                        (None, false) => 0,
+                        (None, true) => len - 1,
+                        (Some(i), false) => (i + 1) % len,
+                        (Some(i), true) => (i + len - 1) % len,
+                    };
+                    popup.at = Some(next);
+                    line = Llinell::new(&popup.choices[next].chars().collect::<Vec<_>>());
+                }
+                _ if tab => {
+                    let text: String = line.buff.iter().collect();
+                    let whole = text.is_empty();
+                    let choices = if whole { self.recent.clone() } else { paths::complete(&text) };
+                    let common = paths::common_prefix(&choices);
+                    if common.len() > text.len() {
+                        line = Llinell::new(&common.chars().collect::<Vec<_>>());
+                    }
+                    if choices.len() > 1 {
+                        popup = Some(Popup { choices, at: None, whole, drawn: 0 });
+                    }
+                }
+                (Key::Char('\r' | '\n'), _) => break,
+                (Key::Escape, _) => return Ok(None),
+                // alt-backspace, ctrl-w
+                (Key::Char('\x7f' | '\x08'), true) | (Key::Char('\x17'), false) => {
+                    let from = line.word_left();
+                    self.kill = line.kill(from, line.cur);
+                }
+                (Key::Char('d'), true) => {
+                    let to = line.word_right();
+                    self.kill = line.kill(line.cur, to);
+                }
+                (Key::Char('\x7f' | '\x08'), false) => line.backspace(),
+                // ctrl-d
+                (Key::Char('\x04'), false) | (Key::Delete, _) => line.delete(),
+                // ctrl-a, ctrl-e
+                (Key::Char('\x01'), false) | (Key::Home, _) => line.cur = 0,
+                (Key::Char('\x05'), false) | (Key::End, _) => line.cur = line.buff.len(),
+                (Key::Char('b'), true) | (Key::Left, _) if word => line.cur = line.word_left(),
+                (Key::Char('f'), true) | (Key::Right, _) if word => line.cur = line.word_right(),
+                // ctrl-b, ctrl-f
+                (Key::Char('\x02'), false) | (Key::Left, _) => line.left(),
+                (Key::Char('\x06'), false) | (Key::Right, _) => line.right(),
+                // ctrl-k, ctrl-u, ctrl-y
+                (Key::Char('\x0b'), false) => self.kill = line.kill(line.cur, line.buff.len()),
+                (Key::Char('\x15'), false) => self.kill = line.kill(0, line.cur),
+                (Key::Char('\x19'), false) => {
+                    for &c in &self.kill {
+                        line.insert(c);
+                    }
+                }
+                // ctrl-p, ctrl-n
+                (Key::Char('\x10'), false) | (Key::Up, _) if at > 0 => {
+                    lines[at] = std::mem::take(&mut line.buff);
+                    at -= 1;
+                    line = Llinell::new(&lines[at]);
+                }
+                (Key::Char('\x0e'), false) | (Key::Down, _) if at + 1 < lines.len() => {
+                    lines[at] = std::mem::take(&mut line.buff);
+                    at += 1;
+                    line = Llinell::new(&lines[at]);
+                }
+                (Key::Char(c), false) if !c.is_control() => line.insert(c),
+                _ => (),
+            }
+        }
+        let out: String = line.buff.into_iter().collect();
+        if !out.is_empty() && hanes.last() != Some(&out) {
+            if hanes.len() == HANES {
+                hanes.remove(0);
+            }
+            hanes.push(out.clone());
+        }
+        Ok(Some(out))
+    }
+}
+
+impl Popup {
+    // Lists the choices on the rows under the prompt, as many as fit,
+    // scrolled so the one picked is shown
+    fn draw<W: Write>(&mut self, outp: &mut W, height: usize, width: usize) -> Result<()> {
+        // between the prompt and the status bar
+        let rows = height.saturating_sub(2).min(self.choices.len());
+        let at = self.at.unwrap_or(0);
+        let top = (at + 1).saturating_sub(rows);
+        for (row, (i, x)) in self.choices.iter().enumerate().skip(top).take(rows).enumerate() {
+            let name = if self.whole {
+                x.as_str()
+            } else {
+                let end = x.trim_end_matches('/');
+                &x[end.rfind('/').map_or(0, |i| i + 1)..]
+            };
+            let name: String = name.chars().take(width.saturating_sub(2)).collect();
+            let on = if self.at == Some(i) { "\x1b[7m" } else { "" };
+            write!(outp, "\x1b[{};1H\x1b[2K {on}{name}\x1b[0m", row + 2)?;
+        }
+        self.drawn = rows;
+        Ok(())
+    }
+    // Blanks the rows it took up, the tree under them is drawn again once
+    // the prompt is done
+    fn clear<W: Write>(&self, outp: &mut W) -> Result<()> {
+        for row in 0..self.drawn {
+            write!(outp, "\x1b[{};1H\x1b[2K", row + 2)?;
+        }
+        Ok(())
+    }
+}
+
+struct Llinell {
+    buff: Vec<char>,
+    cur: usize,
+    // the first character shown, when the line is wider than the terminal
+    scroll: usize,
+}
+
+impl Llinell {
+    fn new(initial: &[char]) -> Self {
+        Llinell { cur: initial.len(), buff: initial.to_vec(), scroll: 0 }
+    }
+    fn insert(&mut self, c: char) {
+        self.buff.insert(self.cur, c);
+        self.cur += 1;
+    }
+    fn backspace(&mut self) {
+        if self.cur > 0 {
+            self.cur -= 1;
+            self.buff.remove(self.cur);
+        }
+    }
+    fn delete(&mut self) {
+        if self.cur < self.buff.len() {
+            self.buff.remove(self.cur);
+        }
+    }
+    fn left(&mut self) {
+        self.cur = self.cur.saturating_sub(1);
+    }
+    fn right(&mut self) {
+        self.cur = (self.cur + 1).min(self.buff.len());
+    }
+    // where the word before the cursor starts, skipping any gap first
+    fn word_left(&self) -> usize {
+        let mut i = self.cur;
+        while i > 0 && !self.buff[i - 1].is_alphanumeric() {
+            i -= 1;
+        }
+        while i > 0 && self.buff[i - 1].is_alphanumeric() {
+            i -= 1;
+        }
+        i
+    }
+    // where the word after the cursor ends, skipping any gap first
+    fn word_right(&self) -> usize {
+        let mut i = self.cur;
+        while i < self.buff.len() && !self.buff[i].is_alphanumeric() {
+            i += 1;
+        }
+        while i < self.buff.len() && self.buff[i].is_alphanumeric() {
+            i += 1;
+        }
+        i
+    }
+    // takes out `from..to`, the cursor being at one end of it
+    fn kill(&mut self, from: usize, to: usize) -> Vec<char> {
+        self.cur = from;
+        self.buff.drain(from..to).collect()
+    }
+    fn draw<W: Write>(&mut self, outp: &mut W, label: &str, width: usize) -> Result<()> {
+        let label_cols: usize = label.chars().map(columns).sum();
+        // leave the last column for the cursor to sit in
+        let room = width.saturating_sub(label_cols + 1).max(1);
+        // scroll only as far as it takes to keep the cursor on screen
+        self.scroll = self.scroll.min(self.cur);
+        while self.buff[self.scroll..self.cur].iter().copied().map(columns).sum::<usize>() >= room {
+            self.scroll += 1;
+        }
+        let mut used = 0;
+        let text: String = self.buff[self.scroll..]
+            .iter()
+            .take_while(|&&c| {
+                used += columns(c);
+                used <= room
+            })
+            .collect();
+        let col = label_cols + self.buff[self.scroll..self.cur].iter().copied().map(columns).sum::<usize>();
+        // columns count from 1
+        write!(outp, "\x1b[H\x1b[2K\x1b[0m{label}{text}\x1b[{}G", col + 1)?;
+        outp.flush()
+    }
+}
@@ -0,0 +1,57 @@
+//! Registers for cut, copy and paste, as in vim.
+//!
+//! Every cut or copy lands in the unnamed register `"` as well as any
+//! register that was named for it, so a plain paste always gets the last
+//! thing taken.
+
+use std::collections::HashMap;
+
+use crate::TodoItem;
+
+/// The register used when none is named.
+pub const UNNAMED: char = '"';
+
+/// Items that have been cut or copied, by register name.
+#[derive(Default)]
+pub struct Registers {
+    held: HashMap<char, TodoItem>,
+}
+
+impl Registers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Whether `name` can be used as a register: `"`, a lowercase letter or
+    /// a digit.
+    pub fn valid(name: char) -> bool {
+        name == UNNAMED || name.is_ascii_lowercase() || name.is_ascii_digit()
+    }
+    /// Stores `item` in register `name`, or only the unnamed one for `None`.
+    pub fn set(&mut self, name: Option<char>, item: TodoItem) {
+        if let Some(name) = name.filter(|&c| c != UNNAMED) {
+            self.held.insert(name, item.clone());
+        }
+        self.held.insert(UNNAMED, item);
+    }
+    /// What's in register `name`, the unnamed one for `None`.
+    pub fn get(&self, name: Option<char>) -> Option<&TodoItem> {
+        self.held.get(&name.unwrap_or(UNNAMED))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_and_unnamed() {
+        let mut regs = Registers::new();
+        assert_eq!(regs.get(None), None);
+        regs.set(Some('a'), TodoItem::Task(false, String::from("a")));
+        regs.set(None, TodoItem::Task(false, String::from("b")));
+        assert_eq!(regs.get(Some('a')), Some(&TodoItem::Task(false, String::from("a"))));
+        assert_eq!(regs.get(None), Some(&TodoItem::Task(false, String::from("b"))));
+        assert_eq!(regs.get(Some(UNNAMED)), regs.get(None));
+        assert!(Registers::valid('q') && !Registers::valid('Q'));
+    }
+}
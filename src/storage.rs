@@ -0,0 +1,339 @@
+//! Getting lists on and off disk.
+//!
+//! Saves are written to a temporary file next to the target, synced, and then
+//! renamed over it, so a crash or a failed `arbed` never leaves half a list
+//! behind. The replaced versions are kept around as `<file>.<n>.bak`, newest
+//! first. A symlinked list is saved through the link, keeping the file's
+//! permissions.
+//!
+//! The format follows the file name: `.json` is JSON, `.md` is Markdown and
+//! anything else is our own `arbed` format.
+
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::{BufWriter, ErrorKind, Result, Write};
+use std::path::{Path, PathBuf};
+
+use crate::format;
+use crate::json;
+use crate::markdown;
+use crate::TodoItem;
+
+/// How many old versions `save` keeps unless told otherwise.
+pub const DEFAULT_BACKUPS: usize = 3;
+
+/// The ways a list can be stored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Nikgwner,
+    Markdown,
+    Json,
+}
+
+impl Format {
+    /// The format a file name asks for.
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("json") => Format::Json,
+            Some("md") | Some("markdown") => Format::Markdown,
+            _ => Format::Nikgwner,
+        }
+    }
+    // exports and imports are always text, Markdown unless asked for JSON
+    fn text(self) -> Format {
+        match self {
+            Format::Nikgwner => Format::Markdown,
+            x => x,
+        }
+    }
+}
+
+/// Saves `list` to `path`, keeping `backups` old versions.
+pub fn save(list: &TodoItem, path: &Path, backups: usize) -> Result<()> {
+    write_as(list, path, Format::from_path(path), backups)
+}
+
+/// Writes a copy of the list as Markdown or JSON. It isn't where the list
+/// lives so no backups are kept.
+pub fn export(list: &TodoItem, path: &Path) -> Result<()> {
+    write_as(list, path, Format::from_path(path).text(), 0)
+}
+
+/// Reads a list from a Markdown or JSON file.
+pub fn import(path: &Path) -> Result<TodoItem> {
+    read_as(path, Format::from_path(path).text())
+}
+
+fn write_as(list: &TodoItem, path: &Path, fmt: Format, backups: usize) -> Result<()> {
+    write_atomic(path, backups, |out| match fmt {
+        Format::Nikgwner => format::arbed_ffeil(list, out),
+        Format::Markdown => markdown::arbed_md(list, out),
+        Format::Json => json::arbed_json(list, out),
+    })
+}
+
+fn read_as(path: &Path, fmt: Format) -> Result<TodoItem> {
+    if fmt == Format::Nikgwner {
+        let mut file = File::open(path)?;
+        return format::llwytho_ffeil(&mut file).map(|(list, _)| list);
+    }
+    let text = fs::read_to_string(path)?;
+    match fmt {
+        Format::Json => json::llwytho_json(&text),
+        _ => markdown::llwytho_md(&text, &stem(path)),
+    }
+}
+
+fn write_atomic<F>(path: &Path, backups: usize, write: F) -> Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<()>,
+{
+    let path = &resolve(path);
+    let tmp = with_name(path, |name| {
+        let mut out = OsString::from(".");
+        out.push(name);
+        out.push(".tmp");
+        out
+    });
+    let written = write_synced(&tmp, write).and_then(|()| {
+        // keep whatever permissions the list had, a fresh file would get
+        // the defaults
+        if let Ok(meta) = fs::metadata(path) {
+            fs::set_permissions(&tmp, meta.permissions())?;
+        }
+        if backups > 0 && path.exists() {
+            rotate_backups(path, backups)?;
+        }
+        fs::rename(&tmp, path)
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    // make the rename itself durable, not every platform lets us open a
+    // directory for this so it's best effort
+    if let Some(dir) = parent(path) {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+/// Loads the list at `path`. Older format versions are read as they are, and
+/// written back as the current one on the next save.
+pub fn load(path: &Path) -> Result<TodoItem> {
+    read_as(path, Format::from_path(path))
+}
+
+/// Like `load`, but a missing file is a new empty list named after it.
+pub fn open(path: &Path) -> Result<TodoItem> {
+    match load(path) {
+        Ok(list) => Ok(list),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            Ok(TodoItem::Group(stem(path), vec![], false))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Where backup `n` of `path` lives, counting from 1 for the most recently
+/// replaced version.
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    with_name(path, |name| {
+        let mut out = name.to_os_string();
+        out.push(format!(".{n}.bak"));
+        out
+    })
+}
+
+/// Loads backup `n` of `path`. Backups all end in `.bak`, so it's read in
+/// the format `path` itself is.
+pub fn restore(path: &Path, n: usize) -> Result<TodoItem> {
+    read_as(&backup_path(&resolve(path), n), Format::from_path(path))
+}
+
+// Follows `path` if it's a symlink, so saving replaces the file it points
+// at rather than the link, and the backups go beside that file
+fn resolve(path: &Path) -> PathBuf {
+    let mut out = path.to_path_buf();
+    // a limit in case of a loop, `canonicalize` can't be used as the target
+    // may not exist yet
+    for _ in 0..40 {
+        let Ok(target) = fs::read_link(&out) else { break; };
+        out = match parent(&out) {
+            Some(dir) => dir.join(target),
+            None => target,
+        };
+    }
+    out
+}
+
+fn write_synced<F>(path: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<()>,
+{
+    let file = File::create(path)?;
+    let mut out = BufWriter::new(file);
+    write(&mut out)?;
+    out.flush()?;
+    out.get_ref().sync_all()
+}
+
+// shuffles `.1.bak` .. `.n-1.bak` up by one, dropping the oldest, and links
+// the current file in as the new `.1.bak`. The current file stays in place so
+// there's never a moment without it.
+fn rotate_backups(path: &Path, backups: usize) -> Result<()> {
+    for n in (1..backups).rev() {
+        match fs::rename(backup_path(path, n), backup_path(path, n + 1)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            _ => (),
+        }
+    }
+    let newest = backup_path(path, 1);
+    match fs::remove_file(&newest) {
+        Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+        _ => (),
+    }
+    if fs::hard_link(path, &newest).is_err() {
+        fs::copy(path, &newest)?;
+    }
+    Ok(())
+}
+
+fn with_name<F: FnOnce(&OsStr) -> OsString>(path: &Path, f: F) -> PathBuf {
+    let name = path.file_name().unwrap_or(path.as_os_str());
+    path.with_file_name(f(name))
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem().map_or(String::from("todo"), |x| x.to_string_lossy().into_owned())
+}
+
+fn parent(path: &Path) -> Option<&Path> {
+    match path.parent() {
+        Some(dir) if dir.as_os_str().is_empty() => Some(Path::new(".")),
+        dir => dir,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(msg: &str) -> TodoItem {
+        TodoItem::Task(false, String::from(msg))
+    }
+
+    fn list(msg: &str) -> TodoItem {
+        TodoItem::Group(String::from("list"), vec![task(msg)], false)
+    }
+
+    // a fresh directory for one test, removed again by the caller
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nikgwner-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut out: Vec<String> = fs::read_dir(dir).unwrap().map(|x| x.unwrap().file_name().into_string().unwrap()).collect();
+        out.sort();
+        out
+    }
+
+    #[test]
+    fn backups_rotate() {
+        let dir = scratch("rotate");
+        let path = dir.join("list.nik");
+        for i in 0..5 {
+            save(&list(&i.to_string()), &path, 3).unwrap();
+        }
+        assert_eq!(names(&dir), ["list.nik", "list.nik.1.bak", "list.nik.2.bak", "list.nik.3.bak"]);
+        assert_eq!(load(&path).unwrap(), list("4"));
+        // newest first, and "0" has been dropped
+        for n in 1..=3 {
+            assert_eq!(restore(&path, n).unwrap(), list(&(4 - n).to_string()));
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn no_backups() {
+        let dir = scratch("no-backups");
+        let path = dir.join("list.nik");
+        save(&list("a"), &path, 0).unwrap();
+        save(&list("b"), &path, 0).unwrap();
+        assert_eq!(names(&dir), ["list.nik"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failed_write() {
+        let dir = scratch("failed");
+        let path = dir.join("list.nik");
+        save(&list("kept"), &path, 3).unwrap();
+        let e = write_atomic(&path, 3, |out| {
+            out.write_all(b"half a list")?;
+            Err(std::io::Error::other("disk full"))
+        });
+        assert_eq!(e.unwrap_err().to_string(), "disk full");
+        assert_eq!(names(&dir), ["list.nik"]);
+        assert_eq!(load(&path).unwrap(), list("kept"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failed_rotate() {
+        let dir = scratch("failed-rotate");
+        let path = dir.join("list.nik");
+        save(&list("kept"), &path, 3).unwrap();
+        // a directory where the newest backup goes can't be removed
+        fs::create_dir(backup_path(&path, 1)).unwrap();
+        fs::write(backup_path(&path, 1).join("x"), "").unwrap();
+        assert!(save(&list("new"), &path, 1).is_err());
+        assert_eq!(names(&dir), ["list.nik", "list.nik.1.bak"]);
+        assert_eq!(load(&path).unwrap(), list("kept"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = scratch("permissions");
+        let path = dir.join("list.nik");
+        save(&list("a"), &path, 3).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        save(&list("b"), &path, 3).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn through_symlink() {
+        let dir = scratch("symlink");
+        let real = dir.join("real.nik");
+        let link = dir.join("link.nik");
+        save(&list("a"), &real, 3).unwrap();
+        std::os::unix::fs::symlink("real.nik", &link).unwrap();
+        save(&list("b"), &link, 3).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(load(&real).unwrap(), list("b"));
+        assert_eq!(restore(&link, 1).unwrap(), list("a"));
+        assert_eq!(names(&dir), ["link.nik", "real.nik", "real.nik.1.bak"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn restore_text_formats() {
+        let dir = scratch("restore");
+        for name in ["list.nik", "list.json", "list.md"] {
+            let path = dir.join(name);
+            save(&list("first"), &path, DEFAULT_BACKUPS).unwrap();
+            save(&list("second"), &path, DEFAULT_BACKUPS).unwrap();
+            assert_eq!(restore(&path, 1).unwrap(), list("first"), "{name}");
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,677 @@
+// the todo tree itself, and moving a selection around it.
+
+use std::io::{Result, Write};
+
+// characters inside a group's progress bar
+const BAR: usize = 10;
+
+/// An item in a todo list, either a task or a group of more items.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TodoItem {
+    /// A task, and whether it's done.
+    Task(bool, String),
+    /// A titled group, it's done when everything in it is. The `bool` is
+    /// whether it's folded, showing only its own line.
+    Group(String, Vec<TodoItem>, bool),
+}
+impl TodoItem {
+    /// Whether the item is done, for a group whether all of its children are.
+    pub fn completed(&self) -> bool {
+        match self {
+            TodoItem::Task(c, _) => *c,
+            TodoItem::Group(_, xs, _) => {
+                let mut _bv = true;
+                for x in xs {
+                    _bv &= x.completed();
+                }
+                _bv
+            }
+        }
+    }
+    /// Marks the item done or not, a group passes it on to all its children.
+    pub fn complete(&mut self, value: bool) {
+        match self {
+            TodoItem::Task(_, msg) => {
+                *self = TodoItem::Task(value, msg.to_string());
+            }
+            TodoItem::Group(_, xs, _) => {
+                for x in xs.iter_mut() {
+                    x.complete(value)
+                }
+            }
+        }
+    }
+    /// Whether the item is done, not done, or for a group part done.
+    pub fn check(&self) -> Check {
+        let progress = self.progress();
+        if progress.done == progress.total {
+            Check::Done
+        } else if progress.done == 0 {
+            Check::Open
+        } else {
+            Check::Partial
+        }
+    }
+    /// Flips the item between done and not done. A part done group is
+    /// finished off or cleared, whichever `partial` says.
+    pub fn toggle(&mut self, partial: Toggle) {
+        let value = match self.check() {
+            Check::Done => false,
+            Check::Open => true,
+            Check::Partial => partial == Toggle::Complete,
+        };
+        self.complete(value);
+    }
+    /// The task's text or the group's title.
+    pub fn message(&self) -> &str {
+        match self {
+            TodoItem::Task(_, msg) => msg,
+            TodoItem::Group(msg, _, _) => msg,
+        }
+    }
+    /// Replaces the task's text or the group's title, leaving everything
+    /// else about it alone.
+    pub fn set_message(&mut self, value: String) {
+        match self {
+            TodoItem::Task(_, msg) => *msg = value,
+            TodoItem::Group(msg, _, _) => *msg = value,
+        }
+    }
+    /// Draws the tree as an indented checklist, highlighting `sel`.
+    pub fn render<W: Write>(&self, depth: u8, outp: &mut W, sel: Option<&Selection>) -> Result<()> {
+        let lines = self.lines(sel, false);
+        for (i, line) in lines.iter().enumerate() {
+            if line.cursor {
+                outp.write_all(b"\x1b[7m")?;
+            }
+            let tabs = "\t".repeat(depth as usize + line.depth);
+            writeln!(outp, "{tabs}{}", line.text)?;
+            if line.selected && !lines.get(i + 1).is_some_and(|x| x.selected) {
+                outp.write_all(b"\x1b[0m")?;
+            }
+        }
+        Ok(())
+    }
+    /// The tree as the lines `render` draws, top to bottom, leaving out
+    /// anything inside folded groups. Groups with tasks in them show how
+    /// many are done, and with `bars` a progress bar as well.
+    pub fn lines(&self, sel: Option<&Selection>, bars: bool) -> Vec<Line> {
+        let mut out = Vec::new();
+        self.lines_depth(&mut Selection::default(), sel.map(|sel| (sel, 0)), false, bars, &mut out);
+        out
+    }
+    fn lines_depth(&self, path: &mut Selection, sel: Option<(&Selection, usize)>, inside: bool, bars: bool, out: &mut Vec<Line>) {
+        let cursor = sel.is_some_and(|(s, i)| i == s.0.len());
+        let mut text = String::from(match self.check() {
+            Check::Done => "[#] ",
+            Check::Partial => "[-] ",
+            Check::Open => "[ ] ",
+        });
+        text.push_str(self.message());
+        let progress = self.progress();
+        if self.is_group() && progress.total > 0 {
+            text.push_str(&format!(" {}/{}", progress.done, progress.total));
+            if bars {
+                text.push_str(&format!(" {} {}%", progress.bar(BAR), progress.percent()));
+            }
+        }
+        if let TodoItem::Group(_, xs, true) = self {
+            text.push_str(&format!(" (+{})", xs.len()));
+        }
+        out.push(Line { depth: path.0.len(), path: path.clone(), text, cursor, selected: cursor || inside });
+        if let TodoItem::Group(_, xs, false) = self {
+            for (i, x) in xs.iter().enumerate() {
+                let fsel = sel.filter(|(s, d)| !cursor && s.0[*d] == i).map(|(s, d)| (s, d + 1));
+                path.0.push(i);
+                x.lines_depth(path, fsel, cursor || inside, bars, out);
+                path.0.pop();
+            }
+        }
+    }
+    /// The item `sel` points at, if there is one.
+    pub fn get(&self, sel: &Selection) -> Option<&Self> {
+        let mut cur = self;
+        for i in &(sel.0) {
+            match cur {
+                TodoItem::Task(_, _) => {
+                    return None;
+                }
+                TodoItem::Group(_, xs, _) => match xs.get(*i) {
+                    Some(x) => cur = x,
+                    None => {
+                        return None;
+                    }
+                },
+            }
+        }
+        Some(cur)
+    }
+    /// The item `sel` points at, if there is one.
+    pub fn get_mut(&mut self, sel: &Selection) -> Option<&mut Self> {
+        let mut cur = self;
+        for i in &(sel.0) {
+            match cur {
+                TodoItem::Task(_, _) => {
+                    return None;
+                }
+                TodoItem::Group(_, xs, _) => match xs.get_mut(*i) {
+                    Some(x) => cur = x,
+                    None => {
+                        return None;
+                    }
+                },
+            }
+        }
+        Some(cur)
+    }
+    /// The group holding the item `sel` points at, or the root for the root.
+    pub fn get_prior(&self, sel: &Selection) -> Option<&Self> {
+        if sel.0.is_empty() {return Some(self);}
+        let mut cur = self;
+        for i in 0 .. sel.0.len() - 1 {
+            let i = sel.0[i];
+            match cur {
+                TodoItem::Task(_, _) => {
+                    return None;
+                }
+                TodoItem::Group(_, xs, _) => match xs.get(i) {
+                    Some(x) => cur = x,
+                    None => {
+                        return None;
+                    }
+                },
+            }
+        }
+        Some(cur)
+    }
+    /// The group holding the item `sel` points at.
+    pub fn get_prior_mut(&mut self, sel: &Selection) -> Option<&mut Self> {
+        if sel.0.is_empty() {return Some(self);}
+        let mut cur = self;
+        for i in 0..sel.0.len() - 1 {
+            let i = sel.0[i];
+            match cur {
+                TodoItem::Task(_, _) => {
+                    return None;
+                }
+                TodoItem::Group(_, xs, _) => match xs.get_mut(i) {
+                    Some(x) => cur = x,
+                    None => {
+                        return None;
+                    }
+                },
+            }
+        }
+        Some(cur)
+    }
+    /// Adds `value` as the last child, turning a task into a group if needed.
+    pub fn insert(&mut self, value: Self) {
+        match self {
+            TodoItem::Group(_, xs, _) => xs.push(value),
+            TodoItem::Task(_, msg) => {
+                *self = TodoItem::Group(msg.to_string(), vec![value], false);
+            }
+        }
+    }
+    /// Adds `value` as the child at `index`, turning a task into a group if
+    /// needed. Panics if `index` is past the last child, like `Vec::insert`.
+    pub fn insert_at(&mut self, index: usize, value: Self) {
+        match self {
+            TodoItem::Group(_, xs, _) => xs.insert(index, value),
+            TodoItem::Task(_, msg) => {
+                assert_eq!(index, 0, "insert_at past the end of a task");
+                *self = TodoItem::Group(msg.to_string(), vec![value], false);
+            }
+        }
+    }
+    /// Adds `value` as the last child of the item at `sel` and moves `sel`
+    /// on to it. Returns `false` if `sel` doesn't point at anything.
+    pub fn add_child(&mut self, sel: &mut Selection, value: Self) -> bool {
+        let Some(x) = self.get_mut(sel) else { return false; };
+        x.insert(value);
+        let TodoItem::Group(_, xs, _) = x else { unreachable!() };
+        sel.0.push(xs.len() - 1);
+        true
+    }
+    /// Adds `value` just after the item at `sel`, or just before it, and
+    /// moves `sel` on to it. The root has no siblings, so there it's added
+    /// as the last child instead. Returns `false` if `sel` doesn't point at
+    /// anything.
+    pub fn insert_sibling(&mut self, sel: &mut Selection, value: Self, after: bool) -> bool {
+        let Some(end) = sel.get_end() else { return self.add_child(sel, value); };
+        if self.get(sel).is_none() {
+            return false;
+        }
+        let index = if after { end + 1 } else { end };
+        self.get_prior_mut(sel).unwrap().insert_at(index, value);
+        *sel.0.last_mut().unwrap() = index;
+        true
+    }
+    /// Moves the item at `sel` itself, rather than the cursor, and moves
+    /// `sel` along with it. `Up` and `Down` swap it with its previous or
+    /// next sibling, `In` makes it the last child of its previous sibling
+    /// and `Out` makes it the next sibling of its parent. Returns `false`,
+    /// changing nothing, if it has nowhere to go that way.
+    pub fn shift(&mut self, sel: &mut Selection, action: CursMove) -> bool {
+        let Some(end) = sel.get_end() else { return false; };
+        let Some(TodoItem::Group(_, xs, _)) = self.get_prior_mut(sel) else { return false; };
+        if end >= xs.len() {
+            return false;
+        }
+        match action {
+            CursMove::Up if end > 0 => {
+                xs.swap(end - 1, end);
+                *sel.0.last_mut().unwrap() = end - 1;
+            }
+            CursMove::Down if end + 1 < xs.len() => {
+                xs.swap(end, end + 1);
+                *sel.0.last_mut().unwrap() = end + 1;
+            }
+            CursMove::In if end > 0 => {
+                let x = xs.remove(end);
+                let prev = &mut xs[end - 1];
+                prev.insert(x);
+                let TodoItem::Group(_, children, _) = prev else { unreachable!() };
+                *sel.0.last_mut().unwrap() = end - 1;
+                sel.0.push(children.len() - 1);
+            }
+            CursMove::Out if sel.0.len() > 1 => {
+                let x = xs.remove(end);
+                sel.0.pop();
+                let parent = sel.get_end().unwrap();
+                let Some(TodoItem::Group(_, xs, _)) = self.get_prior_mut(sel) else { unreachable!() };
+                xs.insert(parent + 1, x);
+                *sel.0.last_mut().unwrap() = parent + 1;
+            }
+            _ => return false,
+        }
+        true
+    }
+    /// How many of the tasks in the tree are done.
+    pub fn progress(&self) -> Progress {
+        match self {
+            TodoItem::Task(done, _) => Progress { done: *done as usize, total: 1 },
+            TodoItem::Group(_, xs, _) => xs.iter().map(TodoItem::progress).fold(Progress::default(), |a, b| Progress {
+                done: a.done + b.done,
+                total: a.total + b.total,
+            }),
+        }
+    }
+    /// Whether this is a folded group.
+    pub fn folded(&self) -> bool {
+        matches!(self, TodoItem::Group(_, _, true))
+    }
+    /// Folds or unfolds a group, tasks have nothing to fold.
+    pub fn fold(&mut self, value: bool) {
+        if let TodoItem::Group(_, _, folded) = self {
+            *folded = value;
+        }
+    }
+    /// Folds or unfolds every group under this one, but not this one.
+    pub fn fold_all(&mut self, value: bool) {
+        if let TodoItem::Group(_, xs, _) = self {
+            for x in xs {
+                x.fold(value);
+                x.fold_all(value);
+            }
+        }
+    }
+    /// Unfolds the groups `sel` is inside of, so it can be seen.
+    pub fn reveal(&mut self, sel: &Selection) {
+        let mut cur = self;
+        for &i in &sel.0 {
+            let TodoItem::Group(_, xs, folded) = cur else { return; };
+            *folded = false;
+            let Some(x) = xs.get_mut(i) else { return; };
+            cur = x;
+        }
+    }
+    /// Moves `sel` out of any folded groups it's hidden in, on to the
+    /// outermost of them.
+    pub fn surface(&self, sel: &mut Selection) {
+        let mut cur = self;
+        for (depth, &i) in sel.0.iter().enumerate() {
+            match cur {
+                TodoItem::Group(_, _, true) => {
+                    sel.0.truncate(depth);
+                    return;
+                }
+                TodoItem::Group(_, xs, false) => match xs.get(i) {
+                    Some(x) => cur = x,
+                    None => return,
+                },
+                TodoItem::Task(..) => return,
+            }
+        }
+    }
+    pub fn is_group(&self) -> bool {
+        matches!(self, TodoItem::Group(..))
+    }
+    /// Whether `sel` can take a step in the direction of `action`.
+    pub fn check_move(&self, sel: &Selection, action: CursMove) -> bool {
+        match action {
+            CursMove::Down => self.get_prior(sel).and_then(|x| {
+                match x {
+                    TodoItem::Task(_, _) => None,
+                    TodoItem::Group(_, xs, _) => {
+                        if sel.0.is_empty() { return None; }
+                        let prior_ind = sel.0[sel.0.len() - 1];
+                        if prior_ind + 1 < xs.len() {Some(())} else {None}
+                    }
+                }
+            }).is_some(),
+            CursMove::Up   => self.get_prior(sel).and_then(|_| {
+                if sel.0.is_empty() { return None; }
+                let prior_ind = sel.0[sel.0.len() - 1];
+                if prior_ind > 0 {Some(())} else {None}
+            }).is_some(),
+            CursMove::Out  => self.get_prior(sel).is_some(),
+            CursMove::In   => self.get(sel)
+                                  .and_then(|x| match x {
+                                      TodoItem::Group(_, xs, false) if !xs.is_empty() => Some(()),
+                                      _ => None,
+                                  })
+                                  .is_some(),
+        }
+    }
+    /// Moves `sel` if it can, otherwise leaves it be.
+    pub fn do_move(&self, sel: &mut Selection, action: CursMove) {
+        if self.check_move(sel, action) {
+            sel.do_move(action);
+        }
+    }
+
+    /// Removes the item `sel` points at and hands it back. Returns `None`
+    /// if nothing was removed.
+    pub fn delete(&mut self, sel: &Selection) -> Option<Self> {
+        let end = sel.get_end()?;
+        match self.get_prior_mut(sel)? {
+            TodoItem::Group(_, xs, _) if end < xs.len() => Some(xs.remove(end)),
+            _ => None,
+        }
+    }
+
+    /// Steps `sel` to the next line down as rendered: into a group, on to
+    /// the next sibling, or out and on to the next sibling of the nearest
+    /// group that has one.
+    pub fn move_next(&self, sel: &mut Selection) {
+        // in, down, out'n'down
+        if self.check_move(sel, CursMove::In)   {sel.do_move(CursMove::In);   return;}
+        if self.check_move(sel, CursMove::Down) {sel.do_move(CursMove::Down); return;}
+        let mut up = sel.clone();
+        while !up.0.is_empty() {
+            up.do_move(CursMove::Out);
+            if self.check_move(&up, CursMove::Down) {
+                up.do_move(CursMove::Down);
+                *sel = up;
+                return;
+            }
+        }
+    }
+    /// Steps `sel` to the next line up as rendered, the opposite of
+    /// `move_next`.
+    pub fn move_prev(&self, sel: &mut Selection) {
+        // out, up 
+        if self.check_move(sel, CursMove::Up) {
+            sel.do_move(CursMove::Up);
+            while self.check_move(sel, CursMove::In) {
+                sel.do_move(CursMove::In);
+                while self.check_move(sel, CursMove::Down) {
+                    sel.do_move(CursMove::Down);
+                }
+            }
+            return;
+        }
+        if self.check_move(sel, CursMove::Out) {sel.do_move(CursMove::Out);}
+    }
+}
+
+/// The three ways a box can be ticked, see `TodoItem::check`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Check {
+    Done,
+    /// A group with some of its tasks done, but not all.
+    Partial,
+    Open,
+}
+
+/// What `TodoItem::toggle` does to a group that's part done.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Toggle {
+    /// Marks everything in it done.
+    #[default]
+    Complete,
+    /// Marks everything in it not done.
+    Clear,
+}
+
+/// How much of a tree is done. Only tasks count, not the groups they're in.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl Progress {
+    /// How much is done out of 100, an empty group counts as all done the
+    /// same as `completed` has it.
+    pub fn percent(&self) -> usize {
+        (self.done * 100).checked_div(self.total).unwrap_or(100)
+    }
+    /// A bar `width` characters wide between brackets, filled with `#` in
+    /// proportion to how much is done.
+    pub fn bar(&self, width: usize) -> String {
+        let full = (self.done * width).checked_div(self.total).unwrap_or(width);
+        format!("[{}{}]", "#".repeat(full), "-".repeat(width - full))
+    }
+}
+
+/// One line of a rendered tree, see `TodoItem::lines`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    /// How far it's indented, the root is 0.
+    pub depth: usize,
+    /// Where the item is in the tree.
+    pub path: Selection,
+    /// The box and the text, and a count of what's hidden for a folded group.
+    pub text: String,
+    /// Whether this is the selected item.
+    pub cursor: bool,
+    /// Whether it's the selected item or inside it, these are highlighted.
+    pub selected: bool,
+}
+
+/// A path through the tree, the index of the child to take at each level.
+/// The empty path is the root.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Selection(pub Vec<usize>);
+// selected is `depth == Selection.0.len()`
+impl Selection {
+    /// Takes a step without checking it's valid, see `TodoItem::do_move`.
+    pub fn do_move(&mut self, action: CursMove) {
+        // Assumes that it can move
+        let clen = self.0.len();
+        match action {
+            CursMove::Down => self.0[ clen - 1] += 1,
+            CursMove::Up => self.0[clen - 1] -= 1,
+            CursMove::Out => {self.0.pop();},
+            CursMove::In => self.0.push(0),
+        }
+    }
+    /// The index of the selected item within its group.
+    pub fn get_end(&self) -> Option<usize> {
+        self.0.last().copied()
+    }
+}
+
+/// A step the selection can take through the tree.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CursMove {
+    In,
+    Out,
+    Down,
+    Up,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(msg: &str) -> TodoItem {
+        TodoItem::Task(false, String::from(msg))
+    }
+
+    fn group(msg: &str, xs: Vec<TodoItem>) -> TodoItem {
+        TodoItem::Group(String::from(msg), xs, false)
+    }
+
+    #[test]
+    fn insert_sibling() {
+        let mut list = group("root", vec![task("a"), task("b")]);
+        let mut sel = Selection(vec![0]);
+        assert!(list.insert_sibling(&mut sel, task("after a"), true));
+        assert_eq!(sel, Selection(vec![1]));
+        assert!(list.insert_sibling(&mut sel, task("before"), false));
+        assert_eq!(sel, Selection(vec![1]));
+        assert_eq!(list, group("root", vec![task("a"), task("before"), task("after a"), task("b")]));
+        assert!(!list.insert_sibling(&mut Selection(vec![9]), task("x"), true));
+    }
+
+    #[test]
+    fn insert_sibling_at_root() {
+        let mut list = group("root", vec![task("a")]);
+        let mut sel = Selection::default();
+        assert!(list.insert_sibling(&mut sel, task("b"), false));
+        assert_eq!(sel, Selection(vec![1]));
+        assert_eq!(list, group("root", vec![task("a"), task("b")]));
+    }
+
+    #[test]
+    fn shift_swaps() {
+        let mut list = group("root", vec![task("a"), task("b")]);
+        let mut sel = Selection(vec![0]);
+        assert!(!list.shift(&mut sel, CursMove::Up));
+        assert!(list.shift(&mut sel, CursMove::Down));
+        assert_eq!(sel, Selection(vec![1]));
+        assert_eq!(list, group("root", vec![task("b"), task("a")]));
+        assert!(!list.shift(&mut sel, CursMove::Down));
+        assert!(!list.shift(&mut Selection::default(), CursMove::Down));
+    }
+
+    #[test]
+    fn shift_in_and_out() {
+        let mut list = group("root", vec![task("a"), task("b"), task("c")]);
+        let mut sel = Selection(vec![1]);
+        assert!(list.shift(&mut sel, CursMove::In));
+        assert_eq!(sel, Selection(vec![0, 0]));
+        assert_eq!(list, group("root", vec![group("a", vec![task("b")]), task("c")]));
+        assert!(!list.shift(&mut sel, CursMove::In));
+        assert!(list.shift(&mut sel, CursMove::Out));
+        assert_eq!(sel, Selection(vec![1]));
+        assert_eq!(list, group("root", vec![group("a", vec![]), task("b"), task("c")]));
+        assert!(!list.shift(&mut sel, CursMove::Out));
+    }
+
+    #[test]
+    fn delete() {
+        let mut list = group("root", vec![group("a", vec![task("a.1")]), task("b")]);
+        assert_eq!(list.delete(&Selection(vec![0])), Some(group("a", vec![task("a.1")])));
+        assert_eq!(list.delete(&Selection(vec![1])), None);
+        assert_eq!(list.delete(&Selection::default()), None);
+        assert_eq!(list, group("root", vec![task("b")]));
+    }
+
+    #[test]
+    fn folding() {
+        let mut list = group("root", vec![group("a", vec![group("a.1", vec![task("a.1.1")])]), task("b")]);
+        list.fold_all(true);
+        assert!(!list.folded() && list.get(&Selection(vec![0, 0])).unwrap().folded());
+        let mut sel = Selection(vec![0]);
+        assert!(!list.check_move(&sel, CursMove::In));
+        list.move_next(&mut sel);
+        assert_eq!(sel, Selection(vec![1]));
+        list.move_prev(&mut sel);
+        assert_eq!(sel, Selection(vec![0]));
+
+        let mut sel = Selection(vec![0, 0, 0]);
+        list.surface(&mut sel);
+        assert_eq!(sel, Selection(vec![0]));
+        list.reveal(&Selection(vec![0, 0, 0]));
+        assert!(!list.get(&Selection(vec![0])).unwrap().folded());
+        assert!(!list.get(&Selection(vec![0, 0])).unwrap().folded());
+        let mut sel = Selection(vec![0, 0, 0]);
+        list.surface(&mut sel);
+        assert_eq!(sel, Selection(vec![0, 0, 0]));
+    }
+
+    #[test]
+    fn lines() {
+        let mut list = group("root", vec![group("a", vec![task("a.1")]), group("b", vec![task("b.1")])]);
+        list.get_mut(&Selection(vec![1])).unwrap().fold(true);
+        let lines = list.lines(Some(&Selection(vec![0])), false);
+        let texts: Vec<&str> = lines.iter().map(|x| x.text.as_str()).collect();
+        assert_eq!(texts, ["[ ] root 0/2", "[ ] a 0/1", "[ ] a.1", "[ ] b 0/1 (+1)"]);
+        assert_eq!(lines[2].path, Selection(vec![0, 0]));
+        assert_eq!(lines[2].depth, 2);
+        let cursor: Vec<bool> = lines.iter().map(|x| x.cursor).collect();
+        let selected: Vec<bool> = lines.iter().map(|x| x.selected).collect();
+        assert_eq!(cursor, [false, true, false, false]);
+        assert_eq!(selected, [false, true, true, false]);
+    }
+
+    #[test]
+    fn move_next_and_prev() {
+        let list = group("root", vec![group("a", vec![group("a.1", vec![task("a.1.1")]), group("a.2", vec![])]), task("b")]);
+        let order = [vec![], vec![0], vec![0, 0], vec![0, 0, 0], vec![0, 1], vec![1]];
+        let mut sel = Selection::default();
+        for path in &order[1..] {
+            list.move_next(&mut sel);
+            assert_eq!(&sel.0, path);
+        }
+        list.move_next(&mut sel);
+        assert_eq!(sel, Selection(vec![1]));
+        for path in order[..order.len() - 1].iter().rev() {
+            list.move_prev(&mut sel);
+            assert_eq!(&sel.0, path);
+        }
+    }
+
+    #[test]
+    fn progress() {
+        let mut list = group("root", vec![group("a", vec![task("a.1"), task("a.2")]), task("b"), group("c", vec![])]);
+        list.get_mut(&Selection(vec![0, 1])).unwrap().complete(true);
+        assert_eq!(list.progress(), Progress { done: 1, total: 3 });
+        assert_eq!(list.get(&Selection(vec![0])).unwrap().progress(), Progress { done: 1, total: 2 });
+        assert_eq!(list.get(&Selection(vec![2])).unwrap().progress(), Progress { done: 0, total: 0 });
+        assert_eq!(list.progress().percent(), 33);
+        assert_eq!(list.progress().bar(6), "[##----]");
+        assert_eq!(Progress::default().bar(3), "[###]");
+        let texts: Vec<String> = list.lines(None, true).into_iter().map(|x| x.text).collect();
+        assert_eq!(texts[0], "[-] root 1/3 [###-------] 33%");
+        assert_eq!(texts[5], "[#] c");
+    }
+
+    #[test]
+    fn toggle() {
+        let mut part = group("g", vec![task("a"), task("b")]);
+        part.get_mut(&Selection(vec![0])).unwrap().complete(true);
+        assert_eq!(part.check(), Check::Partial);
+        let mut cleared = part.clone();
+        cleared.toggle(Toggle::Clear);
+        assert_eq!(cleared.check(), Check::Open);
+        part.toggle(Toggle::Complete);
+        assert_eq!(part.check(), Check::Done);
+        part.toggle(Toggle::Clear);
+        assert_eq!(part.check(), Check::Open);
+        assert_eq!(group("empty", vec![]).check(), Check::Done);
+    }
+
+    #[test]
+    fn add_child() {
+        let mut list = group("root", vec![task("a")]);
+        let mut sel = Selection(vec![0]);
+        assert!(list.add_child(&mut sel, task("a.1")));
+        assert_eq!(sel, Selection(vec![0, 0]));
+        assert_eq!(list, group("root", vec![group("a", vec![task("a.1")])]));
+    }
+}
@@ -0,0 +1,159 @@
+// the window of the tree that fits on screen. Keeps the selection in view,
+// scrolling only as far as it has to, and wraps titles too long for the
+// terminal on to more rows.
+
+use std::io::{Result, Write};
+
+use nikgwner::Line;
+
+// columns a tab takes, for working out how much of a line fits
+const TAB: usize = 8;
+// wrapped rows line up with the text after the `[ ] `
+const BOX: usize = 4;
+
+pub struct View {
+    // the first line shown
+    top: usize,
+}
+
+impl View {
+    pub fn new() -> Self {
+        View { top: 0 }
+    }
+    // Scrolls so line `cursor` is all on screen, given how many rows each
+    // line takes up
+    fn scroll(&mut self, cursor: usize, rows: &[usize], height: usize) {
+        self.top = self.top.min(cursor);
+        while self.top < cursor && rows[self.top..=cursor].iter().sum::<usize>() > height {
+            self.top += 1;
+        }
+        // don't leave space at the bottom that lines above could use
+        while self.top > 0 && rows[self.top - 1..].iter().sum::<usize>() <= height {
+            self.top -= 1;
+        }
+    }
+    // Draws what fits of `lines` in `height` rows of `width` columns, from
+    // where the cursor is. `depth` indents everything, like for `render`.
+    // Each row starts by moving down one, rather than ending with it, so
+    // a full screen doesn't scroll
+    pub fn draw<W: Write>(&mut self, outp: &mut W, lines: &[Line], depth: usize, height: usize, width: usize) -> Result<()> {
+        let wrapped: Vec<Vec<&str>> = lines
+            .iter()
+            .map(|x| wrap(&x.text, width.saturating_sub((depth + x.depth) * TAB)))
+            .collect();
+        let rows: Vec<usize> = wrapped.iter().map(Vec::len).collect();
+        let cursor = lines.iter().position(|x| x.cursor).unwrap_or(0);
+        self.scroll(cursor, &rows, height);
+        let mut left = height;
+        for (line, parts) in lines.iter().zip(&wrapped).skip(self.top) {
+            let tabs = "\t".repeat(depth + line.depth);
+            for (i, part) in parts.iter().enumerate() {
+                if left == 0 {
+                    return Ok(());
+                }
+                left -= 1;
+                let pad = if i == 0 { "" } else { "    " };
+                if line.selected {
+                    write!(outp, "\r\n\x1b[7m{tabs}{pad}{part}\x1b[0m")?;
+                } else {
+                    write!(outp, "\r\n{tabs}{pad}{part}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Splits `text` into rows of at most `width` columns, breaking at spaces
+// where it can. Rows after the first lose `BOX` columns to the indent
+fn wrap(text: &str, width: usize) -> Vec<&str> {
+    let mut out = vec![];
+    let mut rest = text;
+    let mut room = width;
+    loop {
+        let mut used = 0;
+        let mut end = rest.len();
+        // the last space that fits, to break after
+        let mut space = None;
+        for (i, c) in rest.char_indices() {
+            used += columns(c);
+            if used > room {
+                end = i;
+                break;
+            }
+            if c == ' ' {
+                space = Some(i);
+            }
+        }
+        if end == rest.len() {
+            out.push(rest);
+            return out;
+        }
+        let end = match space {
+            Some(i) if i > 0 => i,
+            // no room for even one character, give up on the rest
+            _ if end == 0 => {
+                out.push("");
+                return out;
+            }
+            _ => end,
+        };
+        out.push(&rest[..end]);
+        rest = rest[end..].trim_start_matches(' ');
+        if rest.is_empty() {
+            return out;
+        }
+        room = width.saturating_sub(BOX);
+    }
+}
+
+// How many columns `c` takes up. East Asian wide characters and most emoji
+// take two, there's no crate here for the full tables
+pub fn columns(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115f | 0x2e80..=0xa4cf | 0xac00..=0xd7a3 | 0xf900..=0xfaff | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60 | 0xffe0..=0xffe6 | 0x1f300..=0x1f64f | 0x1f900..=0x1f9ff | 0x20000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapping() {
+        assert_eq!(wrap("short", 10), ["short"]);
+        assert_eq!(wrap("", 10), [""]);
+        assert_eq!(wrap("abc", 0), [""]);
+        // rows after the first are `BOX` narrower
+        assert_eq!(wrap("aa bb cc dd", 7), ["aa bb", "cc", "dd"]);
+        // a word too long for the row is split
+        assert_eq!(wrap("abcdefghijklmno pq", 10), ["abcdefghij", "klmno", "pq"]);
+        assert_eq!(wrap("漢字漢字漢", 6), ["漢字漢", "字", "漢"]);
+        // no room left after the indent
+        assert_eq!(wrap("abcdefgh", 4), ["abcd", ""]);
+    }
+
+    #[test]
+    fn scrolling() {
+        let mut view = View::new();
+        view.scroll(4, &[1, 1, 1, 1, 1], 3);
+        assert_eq!(view.top, 2);
+        view.scroll(3, &[1, 1, 1, 1, 1], 3);
+        assert_eq!(view.top, 2);
+        view.scroll(0, &[1, 1, 1, 1, 1], 3);
+        assert_eq!(view.top, 0);
+        // the list got shorter, so lines above come back into view
+        view.top = 3;
+        view.scroll(3, &[1, 1, 1, 1], 3);
+        assert_eq!(view.top, 1);
+        // a wrapped line is kept whole
+        view.top = 0;
+        view.scroll(1, &[1, 3, 1], 3);
+        assert_eq!(view.top, 1);
+        // unless it's taller than the screen, then its start is shown
+        view.scroll(0, &[5], 3);
+        assert_eq!(view.top, 0);
+    }
+}